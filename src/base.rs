@@ -2,6 +2,7 @@ mod audio_engine;
 mod audio_processor;
 mod midi_engine;
 mod midi_message;
+mod midi_parser;
 mod midi_receiver;
 mod parameter;
 mod processor_proxy;
@@ -10,6 +11,9 @@ pub use audio_engine::AudioEngine;
 pub use audio_processor::AudioProcessor;
 pub use midi_engine::MidiEngine;
 pub use midi_message::MidiMessage;
+pub use midi_parser::MidiParser;
 pub use midi_receiver::MidiReceiver;
 pub use parameter::{Parameter, ParameterId, ParameterValue};
+#[allow(unused_imports)]
+pub use processor_proxy::EventTime;
 pub use processor_proxy::{ProcessorProxy, ProcessorProxySource};
@@ -1,2 +1,3 @@
 /// Defines a generic audio engine that interfaces between an audio processor and hardware.
+#[allow(dead_code)]
 pub trait AudioEngine {}
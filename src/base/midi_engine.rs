@@ -1,2 +1,3 @@
 /// Defines a generic MIDI engine that interfaces between a MIDI receiver and hardware.
+#[allow(dead_code)]
 pub trait MidiEngine {}
@@ -1,6 +1,15 @@
 /// MIDI message type.
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum MidiMessage {
+    /// Active sensing (keep-alive sent by some devices).
+    ActiveSensing,
+
+    /// Channel pressure (aftertouch): channel, pressure.
+    ChannelPressure(u8, u8),
+
+    /// Continue playback from current song position.
+    Continue,
+
     /// Control change: channel, control number, value.
     ControlChange(u8, u8, u8),
 
@@ -9,19 +18,117 @@ pub enum MidiMessage {
 
     /// Note on: channel, note number, velocity.
     NoteOn(u8, u8, u8),
+
+    /// Pitch bend: channel, 14 bit value (8192 is center).
+    PitchBend(u8, u16),
+
+    /// Polyphonic key pressure (aftertouch): channel, note number, pressure.
+    PolyAftertouch(u8, u8, u8),
+
+    /// Program change: channel, program number.
+    ProgramChange(u8, u8),
+
+    /// Song position pointer: 14 bit position in MIDI beats (sixteenth notes).
+    SongPosition(u16),
+
+    /// Start playback from beginning of song.
+    Start,
+
+    /// Stop playback.
+    Stop,
+
+    /// System exclusive: data between start (0xF0) and end (0xF7) byte, including manufacturer ID.
+    SystemExclusive(Box<[u8]>),
+
+    /// Timing clock (24 per quarter note).
+    TimingClock,
 }
 impl MidiMessage {
-    /// Parses raw bytes to a MIDI message, returns [None] if unsupported.
+    /// Returns channel of channel messages, [None] for system messages.
+    pub fn channel(&self) -> Option<u8> {
+        match self {
+            MidiMessage::ChannelPressure(channel, ..)
+            | MidiMessage::ControlChange(channel, ..)
+            | MidiMessage::NoteOff(channel, ..)
+            | MidiMessage::NoteOn(channel, ..)
+            | MidiMessage::PitchBend(channel, ..)
+            | MidiMessage::PolyAftertouch(channel, ..)
+            | MidiMessage::ProgramChange(channel, ..) => Some(*channel),
+            _ => None,
+        }
+    }
+
+    /// Parses raw bytes of one complete message to a MIDI message, returns [None] if invalid or unsupported.
     pub fn from_bytes(raw_bytes: &[u8]) -> Option<MidiMessage> {
-        if let [status, data1, data2] = raw_bytes {
-            match status & 0xF0 {
-                0x80 => Some(MidiMessage::NoteOff(status & 0x0F, *data1, *data2)),
-                0x90 => Some(MidiMessage::NoteOn(status & 0x0F, *data1, *data2)),
-                0xB0 => Some(MidiMessage::ControlChange(status & 0x0F, *data1, *data2)),
+        let (status, data) = raw_bytes.split_first()?;
+        if data.iter().any(|byte| byte & 0x80 != 0) {
+            // Only system exclusive end byte may follow the data.
+            return match (status, data.split_last()) {
+                (0xF0, Some((0xF7, sysex))) if sysex.iter().all(|byte| byte & 0x80 == 0) => {
+                    Some(MidiMessage::SystemExclusive(sysex.into()))
+                }
                 _ => None,
+            };
+        }
+
+        let channel = status & 0x0F;
+        match (status & 0xF0, data) {
+            (0x80, [note, velocity]) => Some(MidiMessage::NoteOff(channel, *note, *velocity)),
+            (0x90, [note, velocity]) => Some(MidiMessage::NoteOn(channel, *note, *velocity)),
+            (0xA0, [note, pressure]) => Some(MidiMessage::PolyAftertouch(channel, *note, *pressure)),
+            (0xB0, [control, value]) => Some(MidiMessage::ControlChange(channel, *control, *value)),
+            (0xC0, [program]) => Some(MidiMessage::ProgramChange(channel, *program)),
+            (0xD0, [pressure]) => Some(MidiMessage::ChannelPressure(channel, *pressure)),
+            (0xE0, [lsb, msb]) => Some(MidiMessage::PitchBend(channel, *lsb as u16 | (*msb as u16) << 7)),
+            (0xF0, _) => match (status, data) {
+                (0xF2, [lsb, msb]) => Some(MidiMessage::SongPosition(*lsb as u16 | (*msb as u16) << 7)),
+                (0xF8, []) => Some(MidiMessage::TimingClock),
+                (0xFA, []) => Some(MidiMessage::Start),
+                (0xFB, []) => Some(MidiMessage::Continue),
+                (0xFC, []) => Some(MidiMessage::Stop),
+                (0xFE, []) => Some(MidiMessage::ActiveSensing),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Encodes message to raw bytes (always with status byte), data bytes are masked to 7 bits.
+    #[allow(dead_code)]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let status = |status: u8, channel: u8| status | (channel & 0x0F);
+        let split = |value: u16| [(value & 0x7F) as u8, (value >> 7 & 0x7F) as u8];
+        match self {
+            MidiMessage::ActiveSensing => vec![0xFE],
+            MidiMessage::ChannelPressure(channel, pressure) => vec![status(0xD0, *channel), pressure & 0x7F],
+            MidiMessage::Continue => vec![0xFB],
+            MidiMessage::ControlChange(channel, control, value) => {
+                vec![status(0xB0, *channel), control & 0x7F, value & 0x7F]
+            }
+            MidiMessage::NoteOff(channel, note, velocity) => vec![status(0x80, *channel), note & 0x7F, velocity & 0x7F],
+            MidiMessage::NoteOn(channel, note, velocity) => vec![status(0x90, *channel), note & 0x7F, velocity & 0x7F],
+            MidiMessage::PitchBend(channel, value) => {
+                let [lsb, msb] = split(*value);
+                vec![status(0xE0, *channel), lsb, msb]
+            }
+            MidiMessage::PolyAftertouch(channel, note, pressure) => {
+                vec![status(0xA0, *channel), note & 0x7F, pressure & 0x7F]
+            }
+            MidiMessage::ProgramChange(channel, program) => vec![status(0xC0, *channel), program & 0x7F],
+            MidiMessage::SongPosition(position) => {
+                let [lsb, msb] = split(*position);
+                vec![0xF2, lsb, msb]
+            }
+            MidiMessage::Start => vec![0xFA],
+            MidiMessage::Stop => vec![0xFC],
+            MidiMessage::SystemExclusive(data) => {
+                let mut bytes = Vec::with_capacity(data.len() + 2);
+                bytes.push(0xF0);
+                bytes.extend(data.iter().map(|byte| byte & 0x7F));
+                bytes.push(0xF7);
+                bytes
             }
-        } else {
-            None
+            MidiMessage::TimingClock => vec![0xF8],
         }
     }
 }
@@ -41,6 +148,15 @@ mod tests {
 
         let invalid_length = MidiMessage::from_bytes(&[0x81]);
         assert_eq!(invalid_length, None);
+
+        let invalid_data = MidiMessage::from_bytes(&[0x91, 0x48, 0x92]);
+        assert_eq!(invalid_data, None);
+
+        let unterminated_sysex = MidiMessage::from_bytes(&[0xF0, 0x7E, 0x01]);
+        assert_eq!(unterminated_sysex, None);
+
+        let empty = MidiMessage::from_bytes(&[]);
+        assert_eq!(empty, None);
     }
 
     #[test]
@@ -53,5 +169,53 @@ mod tests {
 
         let control_change = MidiMessage::from_bytes(&[0xB3, 0x12, 0x36]);
         assert_eq!(control_change, Some(MidiMessage::ControlChange(0x03, 0x12, 0x36)));
+
+        let pitch_bend = MidiMessage::from_bytes(&[0xE2, 0x01, 0x40]);
+        assert_eq!(pitch_bend, Some(MidiMessage::PitchBend(0x02, 8193)));
+
+        let poly_aftertouch = MidiMessage::from_bytes(&[0xA4, 0x3C, 0x50]);
+        assert_eq!(poly_aftertouch, Some(MidiMessage::PolyAftertouch(0x04, 0x3C, 0x50)));
+
+        let program_change = MidiMessage::from_bytes(&[0xC5, 0x07]);
+        assert_eq!(program_change, Some(MidiMessage::ProgramChange(0x05, 0x07)));
+
+        let channel_pressure = MidiMessage::from_bytes(&[0xD6, 0x22]);
+        assert_eq!(channel_pressure, Some(MidiMessage::ChannelPressure(0x06, 0x22)));
+
+        let sysex = MidiMessage::from_bytes(&[0xF0, 0x7E, 0x7F, 0x09, 0x01, 0xF7]);
+        assert_eq!(sysex, Some(MidiMessage::SystemExclusive(vec![0x7E, 0x7F, 0x09, 0x01].into())));
+
+        let song_position = MidiMessage::from_bytes(&[0xF2, 0x10, 0x01]);
+        assert_eq!(song_position, Some(MidiMessage::SongPosition(144)));
+
+        assert_eq!(MidiMessage::from_bytes(&[0xF8]), Some(MidiMessage::TimingClock));
+        assert_eq!(MidiMessage::from_bytes(&[0xFA]), Some(MidiMessage::Start));
+        assert_eq!(MidiMessage::from_bytes(&[0xFB]), Some(MidiMessage::Continue));
+        assert_eq!(MidiMessage::from_bytes(&[0xFC]), Some(MidiMessage::Stop));
+        assert_eq!(MidiMessage::from_bytes(&[0xFE]), Some(MidiMessage::ActiveSensing));
+    }
+
+    #[test]
+    fn to_bytes_round_trip() {
+        let messages = [
+            MidiMessage::ActiveSensing,
+            MidiMessage::ChannelPressure(0x0F, 0x7F),
+            MidiMessage::Continue,
+            MidiMessage::ControlChange(0x03, 0x12, 0x36),
+            MidiMessage::NoteOff(0x01, 0x48, 0x12),
+            MidiMessage::NoteOn(0x0A, 0x52, 0x24),
+            MidiMessage::PitchBend(0x02, 16383),
+            MidiMessage::PolyAftertouch(0x04, 0x3C, 0x50),
+            MidiMessage::ProgramChange(0x05, 0x07),
+            MidiMessage::SongPosition(1234),
+            MidiMessage::Start,
+            MidiMessage::Stop,
+            MidiMessage::SystemExclusive(vec![0x41, 0x10, 0x42].into()),
+            MidiMessage::TimingClock,
+        ];
+        for message in messages {
+            assert_eq!(MidiMessage::from_bytes(&message.to_bytes()), Some(message));
+        }
+        assert_eq!(MidiMessage::PitchBend(0x00, 8192).to_bytes(), vec![0xE0, 0x00, 0x40]);
     }
 }
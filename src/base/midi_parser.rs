@@ -0,0 +1,127 @@
+use crate::base::MidiMessage;
+
+/// Decodes MIDI byte streams to messages, including running status, interleaved real-time and system exclusive messages.
+#[derive(Debug, Default)]
+pub struct MidiParser {
+    /// Data bytes received for current message.
+    data: Vec<u8>,
+
+    /// Status of current channel message, kept after message is complete (running status).
+    running_status: Option<u8>,
+
+    /// Status of current system common or system exclusive message.
+    system_status: Option<u8>,
+}
+impl MidiParser {
+    /// Creates a new MIDI parser.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses bytes, returns complete messages (partial messages are continued by next call).
+    pub fn parse<'a>(&'a mut self, bytes: &'a [u8]) -> impl Iterator<Item = MidiMessage> + 'a {
+        bytes.iter().filter_map(move |byte| self.push(*byte))
+    }
+
+    /// Parses next byte, returns message if it completes one.
+    pub fn push(&mut self, byte: u8) -> Option<MidiMessage> {
+        match byte {
+            // Real-time messages may appear anywhere and don't affect current message.
+            0xF8..=0xFF => MidiMessage::from_bytes(&[byte]),
+            0xF7 => {
+                let sysex = self.system_status.take() == Some(0xF0);
+                sysex.then(|| MidiMessage::SystemExclusive(self.data.drain(..).collect()))
+            }
+            0xF0..=0xF6 => {
+                // System common messages cancel running status.
+                self.data.clear();
+                self.running_status = None;
+                self.system_status = Some(byte);
+                self.complete_message()
+            }
+            0x80..=0xEF => {
+                self.data.clear();
+                self.running_status = Some(byte);
+                self.system_status = None;
+                None
+            }
+            _ if self.system_status.is_some() || self.running_status.is_some() => {
+                self.data.push(byte);
+                self.complete_message()
+            }
+            _ => None, // Data without status.
+        }
+    }
+
+    /// Returns message if all data bytes for current status were received.
+    fn complete_message(&mut self) -> Option<MidiMessage> {
+        let status = self.system_status.or(self.running_status)?;
+        let data_length = match status {
+            0xC0..=0xDF | 0xF1 | 0xF3 => 1,
+            0x80..=0xEF | 0xF2 => 2,
+            0xF0 => return None, // System exclusive is terminated by end byte.
+            _ => 0,
+        };
+        if self.data.len() < data_length {
+            return None;
+        }
+
+        let mut bytes = [status, 0, 0];
+        bytes[1..=data_length].copy_from_slice(&self.data);
+        self.data.clear();
+        if self.system_status.take().is_some() {
+            self.running_status = None;
+        }
+        MidiMessage::from_bytes(&bytes[..=data_length])
+    }
+}
+
+/// Unit tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_running_status() {
+        let mut parser = MidiParser::new();
+        let messages: Vec<_> = parser.parse(&[0x90, 0x3C, 0x64, 0x40, 0x50, 0x3C, 0x00, 0xC1, 0x05, 0x06]).collect();
+        assert_eq!(
+            messages,
+            vec![
+                MidiMessage::NoteOn(0x00, 0x3C, 0x64),
+                MidiMessage::NoteOn(0x00, 0x40, 0x50),
+                MidiMessage::NoteOn(0x00, 0x3C, 0x00),
+                MidiMessage::ProgramChange(0x01, 0x05),
+                MidiMessage::ProgramChange(0x01, 0x06),
+            ]
+        );
+
+        // Messages split across calls are continued, system common messages cancel running status.
+        let messages: Vec<_> = parser.parse(&[0xE0, 0x00]).collect();
+        assert!(messages.is_empty());
+        let messages: Vec<_> = parser.parse(&[0x40, 0xF2, 0x10, 0x01, 0x12, 0x34]).collect();
+        assert_eq!(messages, vec![MidiMessage::PitchBend(0x00, 8192), MidiMessage::SongPosition(144)]);
+    }
+
+    #[test]
+    fn parse_interleaved() {
+        // Real-time messages inside channel and system exclusive messages.
+        let mut parser = MidiParser::new();
+        let bytes = [0xB2, 0x07, 0xF8, 0x64, 0xF0, 0x7E, 0xFE, 0x09, 0xF7, 0xFA, 0x01];
+        let messages: Vec<_> = parser.parse(&bytes).collect();
+        assert_eq!(
+            messages,
+            vec![
+                MidiMessage::TimingClock,
+                MidiMessage::ControlChange(0x02, 0x07, 0x64),
+                MidiMessage::ActiveSensing,
+                MidiMessage::SystemExclusive(vec![0x7E, 0x09].into()),
+                MidiMessage::Start,
+            ]
+        );
+
+        // Interrupted system exclusive is dropped.
+        let messages: Vec<_> = parser.parse(&[0xF0, 0x7E, 0x80, 0x3C, 0x00, 0xF7]).collect();
+        assert_eq!(messages, vec![MidiMessage::NoteOff(0x00, 0x3C, 0x00)]);
+    }
+}
@@ -1,7 +1,22 @@
 use crate::base::MidiMessage;
+use std::time::Instant;
 
 /// Defines a generic MIDI receiver, e.g. an instrument or effect that can process MIDI.
 pub trait MidiReceiver: Send {
     /// Handles a MIDI message.
     fn handle_midi_message(&mut self, message: MidiMessage);
+
+    /// Handles a MIDI message that takes effect at frame offset within next processed buffer.
+    ///
+    /// Receivers without sample-accurate timing handle message immediately.
+    fn handle_midi_message_at(&mut self, message: MidiMessage, _frame_offset: usize) {
+        self.handle_midi_message(message);
+    }
+
+    /// Handles a MIDI message received at host time (e.g. timestamped by MIDI driver).
+    ///
+    /// Receivers without sample-accurate timing handle message immediately.
+    fn handle_timed_midi_message(&mut self, message: MidiMessage, _timestamp: Instant) {
+        self.handle_midi_message(message);
+    }
 }
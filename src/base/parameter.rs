@@ -8,6 +8,7 @@ pub struct Parameter {
     pub id: ParameterId,
 
     /// String representation.
+    #[allow(dead_code)]
     pub name: &'static str,
 }
 impl Parameter {
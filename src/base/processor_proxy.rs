@@ -1,18 +1,27 @@
 use crate::base::{AudioProcessor, MidiMessage, MidiReceiver, ParameterId, ParameterValue};
 use std::{
     collections::HashMap,
-    sync::{Arc, Mutex, RwLock},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    time::{Duration, Instant},
 };
 
 /// Encapsulates communication with an audio processor that lives on the audio thread.
 pub struct ProcessorProxy {
+    /// Sample clock of processor, shared with source.
+    clock: Arc<SampleClock>,
+
     /// Parameter map populated by processor messages.
     parameter_map: Arc<RwLock<HashMap<ParameterId, ParameterValue>>>,
 
+    /// Sample rate, buffer size and channel layout of processor, used to prepare swapped processors.
+    processor_setup: Arc<RwLock<ProcessorSetup>>,
+
     /// Channel to send messages to the processor.
     to_source: Arc<Mutex<ringbuf::Producer<ProxyMessage>>>,
 }
-#[allow(dead_code)]
 impl ProcessorProxy {
     /// Creates new processor proxy, returning message loop thread handle.
     fn new(
@@ -21,26 +30,72 @@ impl ProcessorProxy {
     ) -> (Self, std::thread::JoinHandle<()>) {
         let to_source = Arc::new(Mutex::new(to_source));
         let parameter_map = Arc::new(RwLock::new(HashMap::new()));
-        let proxy = ProcessorProxy { parameter_map, to_source };
+        let processor_setup = Arc::new(RwLock::new(ProcessorSetup::default()));
+        let clock = Arc::new(SampleClock::default());
+        let proxy = ProcessorProxy { clock, parameter_map, processor_setup, to_source };
         let message_loop = proxy.start_message_loop(from_source);
         (proxy, message_loop)
     }
 
     /// Gets a parameter.
+    #[allow(dead_code)]
     pub fn get_parameter(&self, id: ParameterId) -> Option<ParameterValue> {
         let parameter_map = self.parameter_map.read().unwrap();
-        if let Some(value) = parameter_map.get(&id) {
-            Some(*value)
-        } else {
-            None
+        parameter_map.get(&id).copied()
+    }
+
+    /// Returns number of scheduled MIDI messages that were dispatched late (at first frame of a later block).
+    #[allow(dead_code)]
+    pub fn late_event_count(&self) -> u64 {
+        self.clock.late_events.load(Ordering::Relaxed)
+    }
+
+    /// Returns sample time of processor, frames processed before current block.
+    #[allow(dead_code)]
+    pub fn sample_time(&self) -> u64 {
+        self.clock.sample_time.load(Ordering::Relaxed)
+    }
+
+    /// Schedules MIDI message to be handled at the frame it is due, late messages are flagged (see [Self::late_event_count]).
+    #[allow(dead_code)]
+    pub fn schedule_midi_message(&mut self, message: MidiMessage, time: EventTime) {
+        if let MidiMessage::SystemExclusive(_) = message {
+            return;
         }
+        self.to_source.lock().unwrap().push(ProxyMessage::ScheduleMidi(message, time)).ok();
     }
 
     /// Sends a parameter change to processor.
+    #[allow(dead_code)]
     pub fn set_parameter(&mut self, id: ParameterId, value: ParameterValue) {
         self.to_source.lock().unwrap().push(ProxyMessage::SetParameter(id, value)).ok();
     }
 
+    /// Sends MIDI message to processor, timed messages are placed within processed buffer.
+    fn send_midi_message(&mut self, message: MidiMessage, timestamp: Option<Instant>) {
+        // System exclusive data would be deallocated on audio thread, processors don't use it.
+        if let MidiMessage::SystemExclusive(_) = message {
+            return;
+        }
+        self.to_source.lock().unwrap().push(ProxyMessage::HandleMidi(message, timestamp)).ok();
+    }
+
+    /// Swaps processor, e.g. a newly loaded instrument (old processor is faded out and dropped off audio thread).
+    ///
+    /// Processor is reset on the calling thread, so that no allocations happen on the audio thread.
+    /// Returns processor if channel is full.
+    pub fn swap_processor(&mut self, mut processor: Box<dyn AudioProcessor>) -> Result<(), Box<dyn AudioProcessor>> {
+        let setup = *self.processor_setup.read().unwrap();
+        processor.set_channel_layout(setup.input_channels, setup.output_channels);
+        processor.reset(setup.sample_rate, setup.max_buffer_size);
+        self.to_source.lock().unwrap().push(ProxyMessage::SwapProcessor(processor)).map_err(|message| match message {
+            ProxyMessage::SwapProcessor(processor) => processor,
+            ProxyMessage::HandleMidi(..) | ProxyMessage::ScheduleMidi(..) | ProxyMessage::SetParameter(..) => {
+                unreachable!()
+            }
+        })
+    }
+
     /// Starts thread that handles messages from processor.
     fn start_message_loop(&self, mut from_source: ringbuf::Consumer<ProcessorMessage>) -> std::thread::JoinHandle<()> {
         let parameter_map = self.parameter_map.clone();
@@ -50,7 +105,10 @@ impl ProcessorProxy {
                 let mut parameter_map = parameter_map.write().unwrap();
                 while let Some(message) = from_source.pop() {
                     match message {
-                        ProcessorMessage::UpdateParameter(id, value) => parameter_map.insert(id, value),
+                        ProcessorMessage::DropProcessor(processor) => drop(processor), // Free memory here.
+                        ProcessorMessage::UpdateParameter(id, value) => {
+                            parameter_map.insert(id, value);
+                        }
                     };
                 }
             }
@@ -59,23 +117,47 @@ impl ProcessorProxy {
 }
 impl Clone for ProcessorProxy {
     fn clone(&self) -> Self {
-        Self { parameter_map: self.parameter_map.clone(), to_source: self.to_source.clone() }
+        Self {
+            clock: self.clock.clone(),
+            parameter_map: self.parameter_map.clone(),
+            processor_setup: self.processor_setup.clone(),
+            to_source: self.to_source.clone(),
+        }
     }
 }
 impl MidiReceiver for ProcessorProxy {
     fn handle_midi_message(&mut self, message: MidiMessage) {
-        self.to_source.lock().unwrap().push(ProxyMessage::HandleMidi(message)).ok();
+        self.send_midi_message(message, None);
+    }
+
+    fn handle_timed_midi_message(&mut self, message: MidiMessage, timestamp: Instant) {
+        self.send_midi_message(message, Some(timestamp));
     }
 }
 
 /// The processor end of the communication channel, serving the proxy.
 pub struct ProcessorProxySource {
+    /// Start time and frame count of block that is processed, used to place timed MIDI messages.
+    block: Option<(Instant, usize)>,
+
     /// Channel to receive messages from proxy.
     from_proxy: ringbuf::Consumer<ProxyMessage>,
 
+    /// Processor received from proxy, waiting to be swapped in.
+    processor_swap: Option<Box<dyn AudioProcessor>>,
+
     /// (First) proxy object with message loop thread handle.
     proxy: (ProcessorProxy, std::thread::JoinHandle<()>),
 
+    /// Sample rate in Hz, used to convert timestamps to frame offsets.
+    sample_rate: f32,
+
+    /// Sample time of current block (frames processed before it).
+    sample_time: u64,
+
+    /// Scheduled MIDI messages that are not due yet.
+    scheduled: Vec<(EventTime, MidiMessage)>,
+
     /// Channel to send messages to proxy.
     to_proxy: ringbuf::Producer<ProcessorMessage>,
 }
@@ -93,7 +175,17 @@ impl ProcessorProxySource {
         // Create proxy.
         let proxy = ProcessorProxy::new(to_source, from_source);
 
-        ProcessorProxySource { from_proxy, proxy, to_proxy }
+        let sample_rate = ProcessorSetup::default().sample_rate;
+        ProcessorProxySource {
+            block: None,
+            from_proxy,
+            processor_swap: None,
+            proxy,
+            sample_rate,
+            sample_time: 0,
+            scheduled: Vec::with_capacity(buffer_size),
+            to_proxy,
+        }
     }
 
     /// Returns a proxy to this source.
@@ -101,16 +193,105 @@ impl ProcessorProxySource {
         self.proxy.0.clone()
     }
 
-    /// Handles proxy messages on a processor.
+    /// Handles proxy messages on a processor, stops at a processor swap (see [Self::take_processor_swap]).
     pub fn handle_messages(&mut self, processor: &mut dyn AudioProcessor) {
-        while let Some(message) = self.from_proxy.pop() {
-            match message {
-                ProxyMessage::HandleMidi(message) => processor.handle_midi_message(message),
-                ProxyMessage::SetParameter(id, value) => processor.set_parameter(id, value),
+        while self.processor_swap.is_none() {
+            match self.from_proxy.pop() {
+                Some(ProxyMessage::HandleMidi(message, None)) => processor.handle_midi_message(message),
+                Some(ProxyMessage::HandleMidi(message, Some(timestamp))) => {
+                    processor.handle_midi_message_at(message, self.frame_offset(timestamp))
+                }
+                Some(ProxyMessage::ScheduleMidi(message, time)) => {
+                    if self.scheduled.len() < self.scheduled.capacity() {
+                        self.scheduled.push((time, message));
+                    } else {
+                        // Queue is full, handle message now and flag it as late.
+                        processor.handle_midi_message(message);
+                        self.proxy.0.clock.late_events.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+                Some(ProxyMessage::SetParameter(id, value)) => processor.set_parameter(id, value),
+                Some(ProxyMessage::SwapProcessor(processor)) => self.processor_swap = Some(processor),
+                None => break,
             };
         }
     }
 
+    /// Dispatches scheduled MIDI messages that are due in current block at their frame offset.
+    ///
+    /// Messages that were due before current block are handled at its first frame and flagged as late.
+    pub fn dispatch_scheduled(&mut self, processor: &mut dyn AudioProcessor) {
+        let frame_count = self.block.map_or(0, |(_, frame_count)| frame_count) as i64;
+        let (block, sample_rate, sample_time) = (self.block, self.sample_rate, self.sample_time);
+        let frame_offset = |time: &EventTime| match time {
+            EventTime::HostTime(time) => match block {
+                Some((start, _)) if *time >= start => {
+                    (time.duration_since(start).as_secs_f64() * sample_rate as f64) as i64
+                }
+                Some((start, _)) => -((start.duration_since(*time).as_secs_f64() * sample_rate as f64).ceil() as i64),
+                None => 0,
+            },
+            EventTime::SampleTime(time) => *time as i64 - sample_time as i64,
+        };
+        for (time, message) in self.scheduled.extract_if(.., |(time, _)| frame_offset(time) < frame_count) {
+            let frame_offset = frame_offset(&time);
+            if frame_offset < 0 {
+                self.proxy.0.clock.late_events.fetch_add(1, Ordering::Relaxed);
+            }
+            processor.handle_midi_message_at(message, frame_offset.max(0) as usize);
+        }
+    }
+
+    /// Starts processing a block, timed MIDI messages received during the previous block period are placed at their offset.
+    ///
+    /// This delays timed messages by one block, trading jitter for constant latency. Advances sample time by previous block.
+    pub fn start_block(&mut self, frame_count: usize) {
+        if let Some((_, previous_frame_count)) = self.block {
+            self.sample_time += previous_frame_count as u64;
+            self.proxy.0.clock.sample_time.store(self.sample_time, Ordering::Relaxed);
+        }
+        let now = Instant::now();
+        let duration = Duration::from_secs_f32(frame_count as f32 / self.sample_rate);
+        self.block = Some((now.checked_sub(duration).unwrap_or(now), frame_count));
+    }
+
+    /// Returns frame offset of timestamp within block, earlier timestamps are placed at first frame.
+    fn frame_offset(&self, timestamp: Instant) -> usize {
+        match self.block {
+            Some((start, frame_count)) => {
+                let offset = timestamp.saturating_duration_since(start).as_secs_f32() * self.sample_rate;
+                (offset as usize).min(frame_count.saturating_sub(1))
+            }
+            None => 0,
+        }
+    }
+
+    /// Returns processor that should be swapped in, remaining messages are meant for this processor.
+    pub fn take_processor_swap(&mut self) -> Option<Box<dyn AudioProcessor>> {
+        self.processor_swap.take()
+    }
+
+    /// Sends processor to proxy to drop it off the audio thread, returns processor if channel is full.
+    pub fn retire_processor(&mut self, processor: Box<dyn AudioProcessor>) -> Result<(), Box<dyn AudioProcessor>> {
+        self.to_proxy.push(ProcessorMessage::DropProcessor(processor)).map_err(|message| match message {
+            ProcessorMessage::DropProcessor(processor) => processor,
+            ProcessorMessage::UpdateParameter(..) => unreachable!(),
+        })
+    }
+
+    /// Stores sample rate and buffer size, used to prepare swapped processors (don't call from audio thread).
+    pub fn reset(&mut self, sample_rate: f32, max_buffer_size: usize) {
+        self.sample_rate = sample_rate;
+        let mut setup = self.proxy.0.processor_setup.write().unwrap();
+        (setup.sample_rate, setup.max_buffer_size) = (sample_rate, max_buffer_size);
+    }
+
+    /// Stores channel layout, used to prepare swapped processors (don't call from audio thread).
+    pub fn set_channel_layout(&mut self, input_channels: u16, output_channels: u16) {
+        let mut setup = self.proxy.0.processor_setup.write().unwrap();
+        (setup.input_channels, setup.output_channels) = (input_channels, output_channels);
+    }
+
     /// Notifies proxy about new changes.
     pub fn notify_proxy(&self) {
         self.proxy.1.thread().unpark();
@@ -122,23 +303,66 @@ impl ProcessorProxySource {
     }
 }
 
+/// Time at which a scheduled MIDI message is due.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventTime {
+    /// Host time, placed like timestamps of MIDI input (one block of latency).
+    #[allow(dead_code)]
+    HostTime(Instant),
+
+    /// Sample time, in frames since processing started (see [ProcessorProxy::sample_time]).
+    #[allow(dead_code)]
+    SampleTime(u64),
+}
+
+/// Sample clock of a processor, shared between source and proxies.
+#[derive(Debug, Default)]
+struct SampleClock {
+    /// Number of scheduled MIDI messages that were dispatched late.
+    late_events: AtomicU64,
+
+    /// Sample time of current block.
+    sample_time: AtomicU64,
+}
+
 /// Messages sent from proxy to processor.
-#[derive(Debug)]
 enum ProxyMessage {
-    /// Handles a MIDI message.
-    HandleMidi(MidiMessage),
+    /// Handles a MIDI message, optionally at host time.
+    HandleMidi(MidiMessage, Option<Instant>),
+
+    /// Schedules a MIDI message.
+    ScheduleMidi(MidiMessage, EventTime),
 
     /// Sets a parameter.
     SetParameter(ParameterId, ParameterValue),
+
+    /// Swaps processor.
+    SwapProcessor(Box<dyn AudioProcessor>),
 }
 
 /// Messages sent from processor to proxy.
-#[derive(Debug)]
 enum ProcessorMessage {
+    /// Drops a processor that has been swapped out.
+    DropProcessor(Box<dyn AudioProcessor>),
+
     /// Updates a parameter.
     UpdateParameter(ParameterId, ParameterValue),
 }
 
+/// Sample rate, buffer size and channel layout of a processor.
+#[derive(Clone, Copy, Debug)]
+struct ProcessorSetup {
+    input_channels: u16,
+    max_buffer_size: usize,
+    output_channels: u16,
+    sample_rate: f32,
+}
+impl Default for ProcessorSetup {
+    fn default() -> Self {
+        ProcessorSetup { input_channels: 0, max_buffer_size: 4096, output_channels: 2, sample_rate: 44100.0 }
+    }
+}
+
 /// Unit tests.
 #[cfg(test)]
 mod tests {
@@ -153,13 +377,67 @@ mod tests {
         let mut processor = DummyProcessor::new();
         let midi_message = MidiMessage::NoteOn(0x01, 0x48, 0x21);
 
-        proxy.handle_midi_message(midi_message);
+        proxy.handle_midi_message(midi_message.clone());
         source.handle_messages(&mut processor);
 
         assert_eq!(processor.midi_messages.len(), 1);
         assert_eq!(processor.midi_messages[0], midi_message);
     }
 
+    #[test]
+    fn send_timed_midi_to_source() {
+        let mut source = ProcessorProxySource::new(8);
+        let mut proxy = source.get_proxy();
+        let mut processor = DummyProcessor::new();
+        source.reset(1000.0, 100);
+
+        // Timed messages are placed within block of 100 ms, starting one block before now.
+        let now = Instant::now();
+        proxy.handle_timed_midi_message(MidiMessage::NoteOn(0x00, 0x3C, 0x40), now - Duration::from_millis(50));
+        proxy.handle_timed_midi_message(MidiMessage::NoteOff(0x00, 0x3C, 0x40), now - Duration::from_millis(500));
+        proxy.handle_midi_message(MidiMessage::NoteOn(0x00, 0x3E, 0x40));
+        source.start_block(100);
+        source.handle_messages(&mut processor);
+
+        assert_eq!(processor.midi_messages.len(), 3);
+        assert!((45..=50).contains(&processor.frame_offsets[0]));
+        assert_eq!(processor.frame_offsets[1..], [0, 0]);
+    }
+
+    #[test]
+    fn schedule_midi() {
+        let mut source = ProcessorProxySource::new(8);
+        let mut proxy = source.get_proxy();
+        let mut processor = DummyProcessor::new();
+        source.reset(1000.0, 100);
+        let process_block = |source: &mut ProcessorProxySource, processor: &mut DummyProcessor| {
+            source.start_block(100);
+            source.handle_messages(processor);
+            source.dispatch_scheduled(processor);
+        };
+
+        // Messages are dispatched in block they are due in, at their frame offset.
+        proxy.schedule_midi_message(MidiMessage::NoteOff(0x00, 0x3C, 0x00), EventTime::SampleTime(150));
+        proxy.schedule_midi_message(MidiMessage::NoteOn(0x00, 0x3C, 0x40), EventTime::SampleTime(20));
+        process_block(&mut source, &mut processor);
+        assert_eq!(processor.midi_messages, vec![MidiMessage::NoteOn(0x00, 0x3C, 0x40)]);
+        assert_eq!(processor.frame_offsets, vec![20]);
+        process_block(&mut source, &mut processor);
+        assert_eq!(processor.midi_messages[1], MidiMessage::NoteOff(0x00, 0x3C, 0x00));
+        assert_eq!(processor.frame_offsets[1], 50);
+        assert_eq!(source.sample_time, 100);
+
+        // Late messages are dispatched at first frame and flagged, host time is placed like MIDI input.
+        proxy.schedule_midi_message(MidiMessage::NoteOn(0x00, 0x3E, 0x40), EventTime::SampleTime(50));
+        let host_time = Instant::now() - Duration::from_millis(50);
+        proxy.schedule_midi_message(MidiMessage::NoteOn(0x00, 0x40, 0x40), EventTime::HostTime(host_time));
+        process_block(&mut source, &mut processor);
+        assert_eq!(processor.frame_offsets[2], 0);
+        assert!((45..=50).contains(&processor.frame_offsets[3]));
+        assert_eq!(proxy.late_event_count(), 1);
+        assert_eq!(proxy.sample_time(), 200);
+    }
+
     #[test]
     fn send_parameter_to_proxies() {
         let mut source = ProcessorProxySource::new(8);
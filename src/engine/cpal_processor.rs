@@ -3,13 +3,34 @@ use crate::base::{
     ProcessorProxySource,
 };
 
+/// Crossfade duration in seconds when swapping processors.
+const CROSSFADE_DURATION: f32 = 0.02;
+
+/// Maximum number of swapped out processors waiting to be sent to proxy.
+const MAX_RETIRED_PROCESSORS: usize = 8;
+
 /// Audio processor wrapper for the cpal audio engine, handling thread synchronization.
 pub struct CpalProcessor {
+    /// Number of output channels.
+    channel_count: u16,
+
+    /// Buffer for rendering processor that is faded out.
+    fade_buffer: Box<[f32]>,
+
+    /// Crossfade length in frames.
+    fade_length: usize,
+
+    /// Swapped out processor that is faded out, with fade position in frames.
+    fading_processor: Option<(Box<dyn AudioProcessor>, usize)>,
+
     /// Wrapped audio processor.
     processor: Box<dyn AudioProcessor>,
 
     /// Proxy source, communicates with proxy objects.
     proxy_source: ProcessorProxySource,
+
+    /// Swapped out processors that could not be sent to proxy yet (channel was full).
+    retired_processors: [Option<Box<dyn AudioProcessor>>; MAX_RETIRED_PROCESSORS],
 }
 impl CpalProcessor {
     /// Creates new cpal processor, including a proxy object to communicate with audio processor.
@@ -17,7 +38,15 @@ impl CpalProcessor {
         // Create proxy source and proxy.
         let proxy_source = ProcessorProxySource::new(256);
         let proxy = proxy_source.get_proxy();
-        let mut cpal_processor = CpalProcessor { processor, proxy_source };
+        let mut cpal_processor = CpalProcessor {
+            channel_count: 0,
+            fade_buffer: Box::new([]),
+            fade_length: 0,
+            fading_processor: None,
+            processor,
+            proxy_source,
+            retired_processors: Default::default(),
+        };
 
         // Fetch initial data.
         cpal_processor.update_proxy();
@@ -25,6 +54,38 @@ impl CpalProcessor {
         (cpal_processor, proxy)
     }
 
+    /// Returns whether a processor can be swapped in, the fading processor must be retired first.
+    fn can_swap_processor(&self) -> bool {
+        self.fading_processor.is_none() || self.retired_processors.iter().any(Option::is_none)
+    }
+
+    /// Sends swapped out processor to proxy, so that it is dropped off the audio thread.
+    ///
+    /// If channel is full, processor waits for next process call. Returns processor if no waiting slot is free.
+    fn retire_processor(&mut self, processor: Box<dyn AudioProcessor>) -> Result<(), Box<dyn AudioProcessor>> {
+        let processor = match self.proxy_source.retire_processor(processor) {
+            Ok(()) => return Ok(()),
+            Err(processor) => processor,
+        };
+        match self.retired_processors.iter_mut().find(|slot| slot.is_none()) {
+            Some(slot) => {
+                *slot = Some(processor);
+                Ok(())
+            }
+            None => Err(processor),
+        }
+    }
+
+    /// Swaps in new processor, old processor is faded out (check [Self::can_swap_processor] first).
+    fn swap_processor(&mut self, processor: Box<dyn AudioProcessor>) {
+        let previous_processor = std::mem::replace(&mut self.processor, processor);
+        if let Some((fading_processor, _)) = self.fading_processor.replace((previous_processor, 0)) {
+            // Swapped again while fading, a waiting slot is free.
+            let retired = self.retire_processor(fading_processor);
+            debug_assert!(retired.is_ok());
+        }
+    }
+
     /// Updates proxy (e.g. parameters might have changed during processing).
     fn update_proxy(&mut self) {
         self.processor.list_parameters().iter().for_each(|p| {
@@ -41,20 +102,67 @@ impl AudioProcessor for CpalProcessor {
         self.processor.list_parameters()
     }
     fn process(&mut self, buffer: &mut [f32]) {
-        // Handle proxy messages.
+        // Handle proxy messages, following messages are meant for swapped in processor.
+        self.proxy_source.start_block(buffer.len() / self.channel_count.max(1) as usize);
         self.proxy_source.handle_messages(&mut *self.processor);
+        // Swaps wait while no processor can be retired, following messages wait with them.
+        while self.can_swap_processor() {
+            let Some(processor) = self.proxy_source.take_processor_swap() else {
+                break;
+            };
+            self.swap_processor(processor);
+            self.proxy_source.handle_messages(&mut *self.processor);
+        }
+        self.proxy_source.dispatch_scheduled(&mut *self.processor);
 
         // Delegate processing to wrapped processor.
         self.processor.process(buffer);
 
+        // Fade out swapped out processor, linear fade is sufficient for this short duration.
+        if let Some((processor, fade_position)) = &mut self.fading_processor {
+            if let Some(fade_buffer) = self.fade_buffer.get_mut(0..buffer.len()) {
+                processor.process(fade_buffer);
+                let channel_count = self.channel_count.max(1) as usize;
+                for (frame, fade_frame) in buffer.chunks_mut(channel_count).zip(fade_buffer.chunks(channel_count)) {
+                    let gain = 1.0 - f32::min(*fade_position as f32 / self.fade_length as f32, 1.0);
+                    frame.iter_mut().zip(fade_frame).for_each(|(sample, fade_sample)| *sample += gain * fade_sample);
+                    *fade_position += 1;
+                }
+            }
+            if *fade_position >= self.fade_length || buffer.len() > self.fade_buffer.len() {
+                let (processor, fade_position) = self.fading_processor.take().unwrap();
+                if let Err(processor) = self.retire_processor(processor) {
+                    self.fading_processor = Some((processor, fade_position)); // Keep fading (silent) until retired.
+                }
+            }
+        }
+        for slot in &mut self.retired_processors {
+            if let Some(processor) = slot.take() {
+                if let Err(processor) = self.proxy_source.retire_processor(processor) {
+                    *slot = Some(processor);
+                    break;
+                }
+            }
+        }
+
         // Update proxy.
         self.update_proxy();
     }
     fn reset(&mut self, sample_rate: f32, max_buffer_size: usize) {
+        self.fade_buffer = vec![0.0; max_buffer_size * self.channel_count as usize].into_boxed_slice();
+        self.fade_length = (CROSSFADE_DURATION * sample_rate) as usize;
+        if let Some((processor, fade_position)) = self.fading_processor.take() {
+            if let Err(processor) = self.retire_processor(processor) {
+                self.fading_processor = Some((processor, fade_position));
+            }
+        }
         self.processor.reset(sample_rate, max_buffer_size);
+        self.proxy_source.reset(sample_rate, max_buffer_size);
     }
     fn set_channel_layout(&mut self, input_channels: u16, output_channels: u16) {
+        self.channel_count = output_channels;
         self.processor.set_channel_layout(input_channels, output_channels);
+        self.proxy_source.set_channel_layout(input_channels, output_channels);
     }
     fn set_parameter(&mut self, id: ParameterId, value: ParameterValue) {
         self.processor.set_parameter(id, value);
@@ -64,6 +172,10 @@ impl MidiReceiver for CpalProcessor {
     fn handle_midi_message(&mut self, message: MidiMessage) {
         self.processor.handle_midi_message(message);
     }
+
+    fn handle_midi_message_at(&mut self, message: MidiMessage, frame_offset: usize) {
+        self.processor.handle_midi_message_at(message, frame_offset);
+    }
 }
 
 /// Unit tests.
@@ -71,7 +183,10 @@ impl MidiReceiver for CpalProcessor {
 mod tests {
     use super::*;
     use crate::test::DummyProcessor;
-    use std::time::Duration;
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
 
     #[test]
     fn forward_parameter_to_processor() {
@@ -95,4 +210,77 @@ mod tests {
 
         assert_eq!(proxy.get_parameter(0), Some(ParameterValue::Float(4.2)));
     }
+
+    #[test]
+    fn swap_processor() {
+        let dropped_on = Arc::new(Mutex::new(None));
+        let wrapped = ConstantProcessor { dropped_on: dropped_on.clone(), value: 1.0 };
+        let (mut processor, mut proxy) = CpalProcessor::new(Box::new(wrapped));
+        processor.set_channel_layout(0, 1);
+        processor.reset(1000.0, 64); // Crossfade takes 20 frames.
+
+        let swapped = ConstantProcessor { dropped_on: Arc::new(Mutex::new(None)), value: 0.5 };
+        assert!(proxy.swap_processor(Box::new(swapped)).is_ok());
+        let mut buffer = [0.0; 64];
+        processor.process(&mut buffer);
+
+        // Old processor fades out, new processor plays at full level.
+        assert_eq!(buffer[0], 1.5);
+        assert!((buffer[10] - 1.0).abs() < 1e-6);
+        assert!(buffer[20..].iter().all(|sample| *sample == 0.5));
+
+        // Old processor is dropped by proxy message loop thread.
+        std::thread::sleep(Duration::from_millis(16));
+        let dropped_on = *dropped_on.lock().unwrap();
+        assert!(dropped_on.is_some());
+        assert_ne!(dropped_on, Some(std::thread::current().id()));
+    }
+
+    #[test]
+    fn swap_processor_channel_full() {
+        let (mut processor, mut proxy) = CpalProcessor::new(Box::new(DummyProcessor::new()));
+        processor.set_channel_layout(0, 1);
+        processor.reset(1000.0, 64);
+
+        // Swap is returned to caller if channel is full.
+        let swap = |proxy: &mut ProcessorProxy, value| {
+            proxy.swap_processor(Box::new(ConstantProcessor { dropped_on: Arc::new(Mutex::new(None)), value }))
+        };
+        while swap(&mut proxy, 0.5).is_ok() {}
+
+        // Queued swaps wait until swapped out processors are retired, last swap plays.
+        let mut buffer = [0.0; 64];
+        (0..300).for_each(|_| processor.process(&mut buffer));
+        assert!(swap(&mut proxy, 1.0).is_ok());
+        processor.process(&mut buffer);
+        assert_eq!(buffer[63], 1.0);
+    }
+
+    /// Processor that outputs a constant value and records the thread it was dropped on.
+    struct ConstantProcessor {
+        dropped_on: Arc<Mutex<Option<std::thread::ThreadId>>>,
+        value: f32,
+    }
+    impl AudioProcessor for ConstantProcessor {
+        fn get_parameter(&self, _id: ParameterId) -> Option<ParameterValue> {
+            None
+        }
+        fn list_parameters(&self) -> &[Parameter] {
+            &[]
+        }
+        fn process(&mut self, buffer: &mut [f32]) {
+            buffer.fill(self.value);
+        }
+        fn reset(&mut self, _sample_rate: f32, _max_buffer_size: usize) {}
+        fn set_channel_layout(&mut self, _input_channels: u16, _output_channels: u16) {}
+        fn set_parameter(&mut self, _id: ParameterId, _value: ParameterValue) {}
+    }
+    impl MidiReceiver for ConstantProcessor {
+        fn handle_midi_message(&mut self, _message: MidiMessage) {}
+    }
+    impl Drop for ConstantProcessor {
+        fn drop(&mut self) {
+            *self.dropped_on.lock().unwrap() = Some(std::thread::current().id());
+        }
+    }
 }
@@ -1,4 +1,11 @@
-use crate::base::{MidiEngine, MidiMessage, MidiReceiver};
+use crate::base::{MidiEngine, MidiParser, MidiReceiver};
+use std::time::{Duration, Instant};
+
+/// Window in which lowest delivery latency of MIDI messages is tracked, to detect drift of driver clock.
+const ANCHOR_WINDOW: Duration = Duration::from_secs(1);
+
+/// Latency above anchor latency that is tolerated as delivery jitter, more is treated as clock drift.
+const ANCHOR_TOLERANCE: Duration = Duration::from_millis(1);
 
 /// MIDI engine based on [midir].
 pub struct MidirMidiEngine {
@@ -16,16 +23,18 @@ impl MidirMidiEngine {
         let mut connections = Vec::<midir::MidiInputConnection<()>>::new();
         for (i, port) in ports.iter().enumerate() {
             let mut receiver = receiver.clone();
+            let mut parser = MidiParser::new();
+            let mut timestamp_map = TimestampMap::new();
             let port_name = format!("sampler_{}", i);
             let midi_input = midir::MidiInput::new(&port_name).expect("Failed to create MIDI input client.");
             let conn = midi_input
                 .connect(
                     port,
                     &port_name,
-                    move |_timestamp, bytes, _data| {
-                        if let Some(message) = MidiMessage::from_bytes(bytes) {
-                            receiver.handle_midi_message(message);
-                        }
+                    move |timestamp, bytes, _data| {
+                        // Some backends deliver several messages at once, possibly using running status.
+                        let time = timestamp_map.map(timestamp, Instant::now());
+                        parser.parse(bytes).for_each(|message| receiver.handle_timed_midi_message(message, time));
                     },
                     (),
                 )
@@ -37,3 +46,87 @@ impl MidirMidiEngine {
     }
 }
 impl MidiEngine for MidirMidiEngine {}
+
+/// Maps driver timestamps (microseconds) to host time, anchored at lowest delivery latency.
+///
+/// Driver clock may run at a slightly different rate than host clock, anchor follows it within [ANCHOR_TOLERANCE].
+struct TimestampMap {
+    /// Host time and timestamp of message with lowest delivery latency, mapped times are relative to it.
+    anchor: Option<(Instant, u64)>,
+
+    /// Start of current window, with lowest latency (relative to anchor) in window and host time and timestamp of it.
+    window: Option<(Instant, Duration, Instant, u64)>,
+}
+impl TimestampMap {
+    /// Creates new timestamp map, first message becomes anchor.
+    fn new() -> Self {
+        TimestampMap { anchor: None, window: None }
+    }
+
+    /// Returns host time of timestamp for message received at host time now, never later than now.
+    fn map(&mut self, timestamp: u64, now: Instant) -> Instant {
+        let time = match self.anchor {
+            Some((anchor_time, anchor_timestamp)) if timestamp >= anchor_timestamp => {
+                anchor_time + Duration::from_micros(timestamp - anchor_timestamp)
+            }
+            _ => now,
+        };
+        if time >= now {
+            // Lower latency than anchor (or driver clock is faster), anchor at this message.
+            self.anchor = Some((now, timestamp));
+            self.window = None;
+            return now;
+        }
+
+        // Driver clock is slower if even the lowest latency of a window grows, move anchor to that message.
+        let latency = now - time;
+        match &mut self.window {
+            Some((start, lowest_latency, lowest_time, lowest_timestamp))
+                if now.duration_since(*start) >= ANCHOR_WINDOW =>
+            {
+                if *lowest_latency > ANCHOR_TOLERANCE {
+                    self.anchor = Some((*lowest_time, *lowest_timestamp));
+                }
+                self.window = Some((now, latency, now, timestamp));
+            }
+            Some((_, lowest_latency, lowest_time, lowest_timestamp)) if latency < *lowest_latency => {
+                (*lowest_latency, *lowest_time, *lowest_timestamp) = (latency, now, timestamp);
+            }
+            Some(_) => (),
+            None => self.window = Some((now, latency, now, timestamp)),
+        }
+        time
+    }
+}
+
+/// Unit tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn map_timestamps() {
+        let mut timestamp_map = TimestampMap::new();
+        let start = Instant::now();
+
+        // Timestamps are placed relative to message with lowest latency.
+        assert_eq!(timestamp_map.map(1000, start), start);
+        assert_eq!(timestamp_map.map(2000, start + Duration::from_micros(1500)), start + Duration::from_micros(1000));
+        assert_eq!(timestamp_map.map(3000, start + Duration::from_micros(1800)), start + Duration::from_micros(1800));
+        assert_eq!(timestamp_map.map(3500, start + Duration::from_micros(2500)), start + Duration::from_micros(2300));
+    }
+
+    #[test]
+    fn map_skewed_timestamps() {
+        let mut timestamp_map = TimestampMap::new();
+        let start = Instant::now();
+
+        // Driver clock runs 0.1% slow, messages are delivered without latency for 100 seconds (drift of 100 ms).
+        // Anchor follows within tolerance and drift of one window.
+        for message in 0..10000u64 {
+            let now = start + Duration::from_millis(10 * message);
+            let time = timestamp_map.map(message * 9990, now);
+            assert!(now - time <= 3 * ANCHOR_TOLERANCE, "Mapped time drifted by {:?}", now - time);
+        }
+    }
+}
@@ -1,3 +1,23 @@
+mod audio_file_format;
+mod load_error;
+mod load_options;
+mod load_progress;
+mod load_report;
+mod multisample_loader;
+mod program_changer;
+mod program_map;
+mod sample_pool;
 mod sfz_loader;
+mod sfz_watcher;
 
+pub use audio_file_format::AudioFileFormat;
+pub use load_error::LoadError;
+pub use load_options::{LoadOptions, SampleStorage};
+pub use load_progress::{LoadCancelled, LoadProgress};
+pub use load_report::LoadReport;
+pub use multisample_loader::MultisampleLoader;
+pub use program_changer::ProgramChanger;
+pub use program_map::ProgramMap;
+pub use sample_pool::{SamplePool, SamplePoolStats};
 pub use sfz_loader::SfzLoader;
+pub use sfz_watcher::SfzWatcher;
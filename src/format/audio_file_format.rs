@@ -0,0 +1,115 @@
+use crate::processing::AudioFileSound;
+use std::{fs::File, io::Read};
+
+/// Audio file formats that can be decoded into an [AudioFileSound].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AudioFileFormat {
+    /// Audio Interchange File Format, including AIFC.
+    Aiff,
+
+    /// Free Lossless Audio Codec.
+    Flac,
+
+    /// MPEG-1/2 Audio Layer III.
+    Mp3,
+
+    /// Vorbis in Ogg container.
+    Vorbis,
+
+    /// RIFF WAVE.
+    Wav,
+}
+impl AudioFileFormat {
+    /// Detects format from file header (magic bytes), falls back to file extension.
+    pub fn detect(header: &[u8], file_path: &str) -> Option<Self> {
+        match header.get(0..4) {
+            Some(b"FORM") => Some(AudioFileFormat::Aiff),
+            Some(b"fLaC") => Some(AudioFileFormat::Flac),
+            Some(b"OggS") => Some(AudioFileFormat::Vorbis),
+            Some(b"RIFF") => Some(AudioFileFormat::Wav),
+            Some([b'I', b'D', b'3', _]) => Some(AudioFileFormat::Mp3), // ID3v2 tag.
+            Some([0xFF, frame_sync, _, _]) if frame_sync & 0xE0 == 0xE0 => Some(AudioFileFormat::Mp3),
+            _ => {
+                let file_path = file_path.to_lowercase();
+                if file_path.ends_with(".aif") || file_path.ends_with(".aiff") || file_path.ends_with(".aifc") {
+                    Some(AudioFileFormat::Aiff)
+                } else if file_path.ends_with(".flac") {
+                    Some(AudioFileFormat::Flac)
+                } else if file_path.ends_with(".mp3") {
+                    Some(AudioFileFormat::Mp3)
+                } else if file_path.ends_with(".ogg") || file_path.ends_with(".oga") {
+                    Some(AudioFileFormat::Vorbis)
+                } else if file_path.ends_with(".wav") {
+                    Some(AudioFileFormat::Wav)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    /// Detects format of a file on disk.
+    pub fn from_file(file_path: &str) -> Option<Self> {
+        let mut header = Vec::with_capacity(4);
+        if let Ok(file) = File::open(file_path) {
+            file.take(4).read_to_end(&mut header).ok();
+        }
+        AudioFileFormat::detect(&header, file_path)
+    }
+
+    /// Decodes audio file data into a sound.
+    pub fn decode(
+        &self,
+        reader: impl Read,
+        midi_region: (u8, u8, u8, u8, u8),
+        adsr: (f32, f32, f32, f32),
+    ) -> Result<AudioFileSound, ()> {
+        match self {
+            AudioFileFormat::Aiff => AudioFileSound::from_aiff_reader(reader, midi_region, adsr).or(Err(())),
+            AudioFileFormat::Flac => AudioFileSound::from_flac_reader(reader, midi_region, adsr).or(Err(())),
+            AudioFileFormat::Mp3 => AudioFileSound::from_mp3_reader(reader, midi_region, adsr).or(Err(())),
+            AudioFileFormat::Vorbis => AudioFileSound::from_vorbis_reader(reader, midi_region, adsr).or(Err(())),
+            AudioFileFormat::Wav => AudioFileSound::from_wav_reader(reader, midi_region, adsr).or(Err(())),
+        }
+    }
+
+    /// Decodes audio file on disk into a sound.
+    pub fn decode_file(
+        &self,
+        file_path: &str,
+        midi_region: (u8, u8, u8, u8, u8),
+        adsr: (f32, f32, f32, f32),
+    ) -> Result<AudioFileSound, ()> {
+        match self {
+            AudioFileFormat::Aiff => AudioFileSound::from_aiff(file_path, midi_region, adsr).or(Err(())),
+            AudioFileFormat::Flac => AudioFileSound::from_flac(file_path, midi_region, adsr).or(Err(())),
+            AudioFileFormat::Mp3 => AudioFileSound::from_mp3(file_path, midi_region, adsr).or(Err(())),
+            AudioFileFormat::Vorbis => AudioFileSound::from_vorbis(file_path, midi_region, adsr).or(Err(())),
+            AudioFileFormat::Wav => AudioFileSound::from_wav(file_path, midi_region, adsr).or(Err(())),
+        }
+    }
+}
+
+/// Unit tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn detect() {
+        assert_eq!(AudioFileFormat::detect(b"fLaC\0\0\0\x22", "sample.wav"), Some(AudioFileFormat::Flac));
+        assert_eq!(AudioFileFormat::detect(b"RIFF\x24\x08\0\0", "sample"), Some(AudioFileFormat::Wav));
+        assert_eq!(AudioFileFormat::detect(b"FORM\0\x02\xEE\x76", "sample"), Some(AudioFileFormat::Aiff));
+        assert_eq!(AudioFileFormat::detect(b"OggS\0\x02\0\0", "sample"), Some(AudioFileFormat::Vorbis));
+        assert_eq!(AudioFileFormat::detect(b"ID3\x04\0\0\0\0", "sample"), Some(AudioFileFormat::Mp3));
+        assert_eq!(AudioFileFormat::detect(b"\xFF\xFB\x94\xC4", "sample"), Some(AudioFileFormat::Mp3));
+        assert_eq!(AudioFileFormat::detect(b"", "Sample.FLAC"), Some(AudioFileFormat::Flac));
+        assert_eq!(AudioFileFormat::detect(b"", "sample.aifc"), Some(AudioFileFormat::Aiff));
+        assert_eq!(AudioFileFormat::detect(b"", "sample.mp4"), None);
+
+        let test_file =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/test/test_sine.flac").to_str().unwrap().to_string();
+        assert_eq!(AudioFileFormat::from_file(&test_file), Some(AudioFileFormat::Flac));
+    }
+}
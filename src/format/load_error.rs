@@ -0,0 +1,39 @@
+use crate::format::LoadCancelled;
+use std::{fmt, io};
+
+/// Error returned if an instrument could not be loaded.
+#[derive(Debug)]
+pub enum LoadError {
+    /// Loading was cancelled.
+    Cancelled,
+
+    /// Instrument file could not be read.
+    Io(io::Error),
+
+    /// Instrument file could not be parsed.
+    Parse(String),
+
+    /// Load options are not supported (e.g. by watcher).
+    Unsupported(String),
+}
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Cancelled => write!(f, "Loading was cancelled"),
+            LoadError::Io(error) => write!(f, "Failed to read instrument: {}", error),
+            LoadError::Parse(message) => write!(f, "Failed to parse instrument: {}", message),
+            LoadError::Unsupported(message) => write!(f, "Unsupported load options: {}", message),
+        }
+    }
+}
+impl std::error::Error for LoadError {}
+impl From<LoadCancelled> for LoadError {
+    fn from(_: LoadCancelled) -> Self {
+        LoadError::Cancelled
+    }
+}
+impl From<io::Error> for LoadError {
+    fn from(error: io::Error) -> Self {
+        LoadError::Io(error)
+    }
+}
@@ -0,0 +1,86 @@
+use crate::format::{LoadProgress, SamplePool};
+use crate::processing::{
+    GlideCurve, Interpolation, NotePriority, PortamentoMode, Sampler, SamplerSound, SamplerVoice, StealPolicy,
+};
+use std::sync::{atomic::AtomicBool, mpsc, Arc};
+
+/// Options for loading instruments.
+#[derive(Clone, Debug, Default)]
+pub struct LoadOptions {
+    /// Loading stops as soon as possible once set, e.g. from another thread.
+    pub cancel: Arc<AtomicBool>,
+
+    /// Interpolation of regions that don't specify `sample_quality`.
+    pub interpolation: Interpolation,
+
+    /// Whether overlapping notes in mono mode change pitch without restarting envelope (legato) or retrigger.
+    pub legato: bool,
+
+    /// Whether sampler starts in mono mode, MIDI mode messages can still switch it.
+    pub mono: bool,
+
+    /// Member channels of lower and upper MPE zone, (0, 0) disables MPE until enabled via MIDI (MPE configuration message).
+    pub mpe_zones: (u8, u8),
+
+    /// Which of the held notes sounds in mono mode.
+    pub note_priority: NotePriority,
+
+    /// Whether highest and lowest playing notes are protected from stealing (e.g. melody and bass).
+    pub note_protection: (bool, bool),
+
+    /// Number of voices playing notes, [None] uses polyphony of instrument (SFZ `polyphony`, 64 by default).
+    pub polyphony: Option<usize>,
+
+    /// Pool of decoded samples, share it to share samples between instruments.
+    pub pool: SamplePool,
+
+    /// Portamento curve, decides how long a glide takes.
+    pub portamento_curve: GlideCurve,
+
+    /// Portamento mode, decides which notes glide.
+    pub portamento_mode: PortamentoMode,
+
+    /// Receives progress while sample files are loaded.
+    pub progress: Option<mpsc::Sender<LoadProgress>>,
+
+    /// Converts decoded samples to engine sample rate when the sampler is reset (slower reset, cheaper playback).
+    pub resample: bool,
+
+    /// Which voice is stolen when polyphony is exhausted.
+    pub steal_policy: StealPolicy,
+
+    /// How sample data is stored.
+    pub storage: SampleStorage,
+}
+impl LoadOptions {
+    /// Applies options that configure playing (e.g. mono mode) to a loaded sampler.
+    pub fn configure_sampler<S: SamplerSound, V: SamplerVoice<S>>(&self, sampler: &mut Sampler<S, V>) {
+        sampler.set_legato(self.legato);
+        sampler.set_mono(self.mono);
+        sampler.set_mpe_zones(self.mpe_zones.0, self.mpe_zones.1);
+        sampler.set_note_priority(self.note_priority);
+        sampler.set_note_protection(self.note_protection.0, self.note_protection.1);
+        sampler.set_portamento_mode(self.portamento_mode, self.portamento_curve);
+        sampler.set_resample(self.resample);
+        sampler.set_steal_policy(self.steal_policy);
+    }
+}
+
+/// Storage of sample data.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum SampleStorage {
+    /// Samples are decoded into memory.
+    #[default]
+    Memory,
+
+    /// Uncompressed WAV samples are memory-mapped, other formats are decoded into memory.
+    ///
+    /// Samples must not be modified while loaded (undefined behavior), so this can't be used with [super::SfzWatcher].
+    Mapped,
+
+    /// Only the first frames of uncompressed, non-looping samples are loaded, the rest is streamed from disk.
+    Stream {
+        /// Frames loaded into memory, must cover reader latency (e.g. 8192 frames).
+        preload_frames: usize,
+    },
+}
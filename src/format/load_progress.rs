@@ -0,0 +1,22 @@
+/// Progress of loading an instrument, sent whenever a sample file starts or finishes loading.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LoadProgress {
+    /// Bytes of sample files loaded (file size on disk).
+    pub bytes_done: u64,
+
+    /// Bytes of all sample files.
+    pub bytes_total: u64,
+
+    /// Sample file that was started or finished last.
+    pub current_file: String,
+
+    /// Sample files loaded, including files that failed to load.
+    pub files_done: usize,
+
+    /// Sample files to load.
+    pub files_total: usize,
+}
+
+/// Error returned if loading was cancelled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LoadCancelled;
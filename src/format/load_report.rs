@@ -0,0 +1,53 @@
+use crate::format::SamplePoolStats;
+use std::{fmt, path::PathBuf, time::Duration};
+
+/// Summary of loading an instrument, including all files it was loaded from.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LoadReport {
+    /// Time it took to load the instrument.
+    pub duration: Duration,
+
+    /// Problems encountered while loading (e.g. missing includes or samples).
+    pub errors: Vec<String>,
+
+    /// Memory usage of sample pool after loading.
+    pub pool_stats: SamplePoolStats,
+
+    /// Regions defined by the instrument.
+    pub region_count: usize,
+
+    /// Sample files referenced by the instrument, including files that failed to load.
+    pub sample_files: Vec<PathBuf>,
+
+    /// Sounds added to the sampler (regions with valid samples).
+    pub sound_count: usize,
+
+    /// Instrument file, followed by included files.
+    pub source_files: Vec<PathBuf>,
+}
+impl LoadReport {
+    /// Returns all files the instrument was loaded from.
+    pub fn files(&self) -> impl Iterator<Item = &PathBuf> {
+        self.source_files.iter().chain(self.sample_files.iter())
+    }
+}
+impl fmt::Display for LoadReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = self.source_files.first().and_then(|path| path.file_name()).unwrap_or_default();
+        write!(
+            f,
+            "Loaded {} in {} ms: {} of {} regions, {} sample files ({:.1} MB decoded, {:.1} MB mapped)",
+            name.to_string_lossy(),
+            self.duration.as_millis(),
+            self.sound_count,
+            self.region_count,
+            self.sample_files.len(),
+            self.pool_stats.decoded_bytes as f64 / 1e6,
+            self.pool_stats.mapped_bytes as f64 / 1e6,
+        )?;
+        for error in self.errors.iter() {
+            write!(f, "\n  {}", error)?;
+        }
+        Ok(())
+    }
+}
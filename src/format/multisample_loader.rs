@@ -0,0 +1,193 @@
+use crate::format::{AudioFileFormat, LoadError, LoadOptions};
+use crate::processing::{
+    AudioFileSound, AudioFileVoice, LoopMode, Sampler, DEFAULT_POLYPHONY, DEFAULT_RESERVED_VOICES,
+};
+use std::{fs::File, io::Read};
+
+/// Loader for Bitwig multisample (`.multisample`) packages.
+pub struct MultisampleLoader {}
+impl MultisampleLoader {
+    /// Creates sampler from multisample package (zip archive with XML manifest and samples).
+    #[allow(dead_code)]
+    pub fn from_file(path: &str) -> Sampler<AudioFileSound, AudioFileVoice> {
+        MultisampleLoader::from_file_with_options(path, &LoadOptions::default())
+            .expect("Failed to load multisample file.")
+    }
+
+    /// Creates sampler from multisample package with load options.
+    ///
+    /// Only options that configure playing apply (e.g. mono mode), samples are decoded from the archive into memory.
+    /// Fails if the package can't be read or its manifest can't be parsed, invalid samples are skipped.
+    pub fn from_file_with_options(
+        path: &str,
+        options: &LoadOptions,
+    ) -> Result<Sampler<AudioFileSound, AudioFileVoice>, LoadError> {
+        // Open archive and read manifest.
+        let file = File::open(path)?;
+        let mut archive = zip::ZipArchive::new(file)
+            .map_err(|error| LoadError::Parse(format!("Invalid multisample archive: {}", error)))?;
+        let mut manifest = String::new();
+        archive
+            .by_name("multisample.xml")
+            .map_err(|_| LoadError::Parse(String::from("Multisample archive does not contain a manifest.")))?
+            .read_to_string(&mut manifest)?;
+        let document = roxmltree::Document::parse(&manifest)
+            .map_err(|error| LoadError::Parse(format!("Invalid multisample manifest: {}", error)))?;
+        let mut sampler = Sampler::new();
+        options.configure_sampler(&mut sampler);
+
+        // Multisample format has no polyphony setting, use option or default (plus voices for fade-out of stolen voices).
+        for _ in 0..options.polyphony.unwrap_or(DEFAULT_POLYPHONY).max(1) + DEFAULT_RESERVED_VOICES {
+            sampler.add_voice(AudioFileVoice::new());
+        }
+        sampler.set_reserved_voices(DEFAULT_RESERVED_VOICES);
+
+        // Add sounds, samples may be grouped in layers.
+        for zone in document.descendants().filter(|node| node.has_tag_name("sample")) {
+            let sound_builder = MultisampleSoundBuilder::from_zone(&zone);
+
+            // Add if valid.
+            if let Ok(sound) = sound_builder.build(&mut archive) {
+                sampler.add_sound(sound);
+            }
+
+            // Debug logging.
+            #[cfg(debug_assertions)]
+            println!("[MultisampleLoader] Added {:?}", sound_builder);
+        }
+
+        Ok(sampler)
+    }
+}
+
+/// Audio file sound builder for a multisample zone.
+#[derive(Debug)]
+struct MultisampleSoundBuilder {
+    file_path: String,
+    gain: f32,
+    high_note: u8,
+    high_velocity: u8,
+    loop_end: Option<usize>,
+    loop_mode: LoopMode,
+    loop_start: usize,
+    low_note: u8,
+    low_velocity: u8,
+    root_note: u8,
+    sample_end: Option<usize>,
+    sample_start: usize,
+    tune: f32,
+}
+impl MultisampleSoundBuilder {
+    /// Creates new sound builder from `<sample>` element.
+    fn from_zone(zone: &roxmltree::Node) -> MultisampleSoundBuilder {
+        let child = |name: &str| zone.children().find(|node| node.has_tag_name(name));
+        let key = child("key");
+        let velocity = child("velocity");
+        let sample_loop = child("loop");
+
+        MultisampleSoundBuilder {
+            file_path: String::from(zone.attribute("file").unwrap_or("")),
+            gain: f32::powf(10.0, parse_attribute::<f32>(Some(*zone), "gain").unwrap_or(0.0) / 20.0),
+            high_note: parse_attribute(key, "high").unwrap_or(127),
+            high_velocity: parse_attribute(velocity, "high").unwrap_or(127),
+            loop_end: parse_attribute::<f64>(sample_loop, "stop").map(|stop| stop as usize),
+            loop_mode: match sample_loop.and_then(|node| node.attribute("mode")) {
+                None | Some("off") => LoopMode::NoLoop,
+                Some(_) => LoopMode::LoopContinuous, // Ping-pong is not supported yet, loop forward instead.
+            },
+            loop_start: parse_attribute::<f64>(sample_loop, "start").unwrap_or(0.0) as usize,
+            low_note: parse_attribute(key, "low").unwrap_or(0),
+            low_velocity: parse_attribute(velocity, "low").unwrap_or(0),
+            root_note: parse_attribute(key, "root").unwrap_or(60),
+            sample_end: parse_attribute::<f64>(Some(*zone), "sample-stop").map(|stop| stop as usize),
+            sample_start: parse_attribute::<f64>(Some(*zone), "sample-start").unwrap_or(0.0) as usize,
+            tune: 100.0 * parse_attribute::<f32>(Some(*zone), "tune").unwrap_or(0.0),
+        }
+    }
+
+    /// Builds sound, decoding the sample directly from the archive.
+    fn build(&self, archive: &mut zip::ZipArchive<File>) -> Result<AudioFileSound, ()> {
+        let mut entry = match archive.by_name(&self.file_path) {
+            Ok(entry) => entry,
+            Err(_) => {
+                eprintln!("Sample not found in archive: {}", self.file_path);
+                return Err(());
+            }
+        };
+
+        // Detect format from header, then decode header and remaining entry data.
+        let mut header = Vec::with_capacity(4);
+        (&mut entry).take(4).read_to_end(&mut header).or(Err(()))?;
+        let format = match AudioFileFormat::detect(&header, &self.file_path) {
+            Some(format) => format,
+            None => {
+                eprintln!("Unsupported audio file format: {}", self.file_path);
+                return Err(());
+            }
+        };
+        let midi_region = (self.root_note, self.low_note, self.high_note, self.low_velocity, self.high_velocity);
+        let adsr = (0.001, 0.0, 0.0, 0.03);
+        let mut sound = match format.decode(header.chain(entry), midi_region, adsr) {
+            Ok(sound) => sound,
+            Err(_) => {
+                eprintln!("Failed to load sample: {}", self.file_path);
+                return Err(());
+            }
+        };
+
+        // Apply zone settings, clamped to sample length.
+        let sample_end = self.sample_end.unwrap_or(sound.duration_samples).min(sound.duration_samples);
+        let loop_end = self.loop_end.unwrap_or(sample_end).min(sample_end);
+        sound.duration_samples = sample_end;
+        sound.gain = self.gain;
+        sound.loop_mode = if self.loop_start < loop_end { self.loop_mode } else { LoopMode::NoLoop };
+        sound.loop_region = (self.loop_start, loop_end);
+        sound.offset = self.sample_start.min(sample_end);
+        sound.tune = self.tune;
+        Ok(sound)
+    }
+}
+
+/// Parses attribute of an optional XML element.
+fn parse_attribute<T: std::str::FromStr>(node: Option<roxmltree::Node>, name: &str) -> Option<T> {
+    node.and_then(|node| node.attribute(name)).and_then(|value| value.trim().parse().ok())
+}
+
+/// Unit tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn from_file() {
+        let test_file =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/test/test.multisample").to_str().unwrap().to_string();
+        let sampler = MultisampleLoader::from_file(&test_file);
+        let sounds = sampler.get_sounds();
+
+        assert_eq!(sounds.len(), 3);
+        assert_eq!(sounds[0].duration_samples, 48000);
+        assert_eq!(sounds[0].midi_region, (29, 24, 32, 1, 47));
+        assert_eq!(sounds[0].loop_mode, LoopMode::NoLoop);
+        assert_eq!(sounds[1].midi_region, (24, 34, 48, 48, 72));
+        assert!((sounds[1].gain - 0.5).abs() < 1e-2);
+        assert_eq!(sounds[1].tune, -50.0);
+        assert_eq!(sounds[2].midi_region, (24, 49, 56, 48, 72));
+        assert_eq!(sounds[2].duration_samples, 24000);
+        assert_eq!(sounds[2].loop_mode, LoopMode::LoopContinuous);
+        assert_eq!(sounds[2].loop_region, (1200, 2400));
+        assert_eq!(sounds[2].offset, 100);
+        assert_eq!(sampler.get_voices().len(), DEFAULT_POLYPHONY + DEFAULT_RESERVED_VOICES);
+    }
+
+    #[test]
+    fn load_errors() {
+        let test_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/test");
+        let load = |file: &str| {
+            MultisampleLoader::from_file_with_options(test_dir.join(file).to_str().unwrap(), &LoadOptions::default())
+        };
+        assert!(matches!(load("missing.multisample"), Err(LoadError::Io(_))));
+        assert!(matches!(load("test.sfz"), Err(LoadError::Parse(_))));
+    }
+}
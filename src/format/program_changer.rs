@@ -0,0 +1,192 @@
+use crate::base::{MidiMessage, MidiReceiver};
+use crate::format::{LoadError, LoadOptions, LoadReport, ProgramMap, SfzLoader};
+use crate::processing::{AudioFileSound, AudioFileVoice, Sampler};
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    thread,
+    time::Instant,
+};
+
+/// MIDI receiver that switches instruments on program change, all messages are forwarded to wrapped receiver.
+///
+/// Bank select (CC 0 and 32) is tracked per channel, mapped instruments are loaded on a background thread.
+#[derive(Clone)]
+pub struct ProgramChanger<R: MidiReceiver + Clone> {
+    /// Bank select (MSB, LSB) of each MIDI channel.
+    banks: Arc<Mutex<[(u8, u8); 16]>>,
+
+    /// Cancels instrument that is currently loading, replaced for each load.
+    cancel: Arc<Mutex<Arc<AtomicBool>>>,
+
+    /// Instrument files by bank and program.
+    map: Arc<ProgramMap>,
+
+    /// Wrapped receiver, e.g. a processor proxy.
+    receiver: R,
+
+    /// Channel to request instrument loads from loader thread.
+    requests: mpsc::Sender<(PathBuf, Arc<AtomicBool>)>,
+}
+impl<R: MidiReceiver + Clone> ProgramChanger<R> {
+    /// Creates new program changer, loaded samplers are passed to callback (e.g. to swap them into the processor).
+    pub fn new<F>(map: ProgramMap, options: LoadOptions, receiver: R, on_load: F) -> Self
+    where
+        F: FnMut(Sampler<AudioFileSound, AudioFileVoice>, LoadReport) + Send + 'static,
+    {
+        let (requests, from_changer) = mpsc::channel();
+        thread::Builder::new()
+            .name(String::from("program-loader"))
+            .spawn(move || ProgramChanger::<R>::run(from_changer, options, on_load))
+            .expect("Failed to start program loader thread.");
+        ProgramChanger {
+            banks: Arc::new(Mutex::new([(0, 0); 16])),
+            cancel: Arc::new(Mutex::new(Arc::new(AtomicBool::new(false)))),
+            map: Arc::new(map),
+            receiver,
+            requests,
+        }
+    }
+
+    /// Loader thread, only the latest request is loaded if requests queue up. Stops once all changers are dropped.
+    ///
+    /// Instruments that fail to load are skipped, so later program changes still load. Pool files that are no longer
+    /// used are purged before and after each load: retired instruments are dropped by the time the next load starts,
+    /// cancelled and failed loads are purged right away.
+    fn run<F>(requests: mpsc::Receiver<(PathBuf, Arc<AtomicBool>)>, options: LoadOptions, mut on_load: F)
+    where
+        F: FnMut(Sampler<AudioFileSound, AudioFileVoice>, LoadReport),
+    {
+        while let Ok(mut request) = requests.recv() {
+            while let Ok(next_request) = requests.try_recv() {
+                request = next_request;
+            }
+            let (path, cancel) = request;
+            let options = LoadOptions { cancel, ..options.clone() };
+            options.pool.purge();
+            match SfzLoader::from_file_with_report(&path.to_string_lossy(), &options) {
+                Ok((sampler, report)) => {
+                    println!("[ProgramChanger] {}", report);
+                    on_load(sampler, report);
+                }
+                Err(LoadError::Cancelled) => println!("[ProgramChanger] Loading {} was cancelled.", path.display()),
+                Err(error) => eprintln!("[ProgramChanger] Failed to load {}: {}", path.display(), error),
+            }
+            options.pool.purge();
+        }
+    }
+
+    /// Handles bank select and program change, loading of previously requested instrument is cancelled.
+    fn handle_program_message(&mut self, message: &MidiMessage) {
+        let mut banks = self.banks.lock().unwrap();
+        match *message {
+            MidiMessage::ControlChange(channel, 0x00, value) => banks[channel as usize & 0x0F].0 = value,
+            MidiMessage::ControlChange(channel, 0x20, value) => banks[channel as usize & 0x0F].1 = value,
+            MidiMessage::ProgramChange(channel, program) => {
+                if let Some(path) = self.map.get(banks[channel as usize & 0x0F], program) {
+                    let mut cancel = self.cancel.lock().unwrap();
+                    cancel.store(true, Ordering::Relaxed);
+                    *cancel = Arc::new(AtomicBool::new(false));
+                    if self.requests.send((path.to_path_buf(), cancel.clone())).is_err() {
+                        eprintln!("[ProgramChanger] Loader thread stopped, program change is ignored.");
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+}
+impl<R: MidiReceiver + Clone> MidiReceiver for ProgramChanger<R> {
+    fn handle_midi_message(&mut self, message: MidiMessage) {
+        self.handle_program_message(&message);
+        self.receiver.handle_midi_message(message);
+    }
+
+    fn handle_timed_midi_message(&mut self, message: MidiMessage, timestamp: Instant) {
+        self.handle_program_message(&message);
+        self.receiver.handle_timed_midi_message(message, timestamp);
+    }
+}
+
+/// Unit tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::base::ProcessorProxySource;
+    use crate::test::DummyProcessor;
+    use std::time::Duration;
+
+    #[test]
+    fn load_program() {
+        let test_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/test");
+        let map = ProgramMap::parse("0 0 0 test.sfz\n1 2 3 test_include.sfz", &test_dir).unwrap();
+        let mut source = ProcessorProxySource::new(8);
+        let (sender, receiver) = mpsc::channel();
+        let mut changer = ProgramChanger::new(map, LoadOptions::default(), source.get_proxy(), move |sampler, _| {
+            sender.send(sampler.get_sounds().len()).unwrap();
+        });
+
+        // Program of selected bank is loaded, unmapped programs are ignored.
+        changer.handle_midi_message(MidiMessage::ProgramChange(0, 1));
+        changer.handle_midi_message(MidiMessage::ControlChange(0, 0x00, 1));
+        changer.handle_midi_message(MidiMessage::ControlChange(0, 0x20, 2));
+        changer.handle_midi_message(MidiMessage::ProgramChange(0, 3));
+        assert_eq!(receiver.recv_timeout(Duration::from_secs(10)), Ok(4));
+        assert!(receiver.recv_timeout(Duration::from_millis(100)).is_err());
+
+        // Messages are forwarded.
+        let mut processor = DummyProcessor::new();
+        source.handle_messages(&mut processor);
+        assert_eq!(processor.midi_messages.len(), 4);
+    }
+
+    #[test]
+    fn skip_invalid_program() {
+        let test_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/test");
+        let map = ProgramMap::parse(
+            "0 0 0 test_invalid.sfz
+0 0 1 missing.sfz
+0 0 2 test.sfz",
+            &test_dir,
+        )
+        .unwrap();
+        let source = ProcessorProxySource::new(8);
+        let (sender, receiver) = mpsc::channel();
+        let mut changer = ProgramChanger::new(map, LoadOptions::default(), source.get_proxy(), move |sampler, _| {
+            sender.send(sampler.get_sounds().len()).unwrap();
+        });
+
+        // Invalid and missing instruments are not loaded, loader keeps running.
+        changer.handle_midi_message(MidiMessage::ProgramChange(0, 0));
+        assert!(receiver.recv_timeout(Duration::from_millis(500)).is_err());
+        changer.handle_midi_message(MidiMessage::ProgramChange(0, 1));
+        assert!(receiver.recv_timeout(Duration::from_millis(500)).is_err());
+        changer.handle_midi_message(MidiMessage::ProgramChange(0, 2));
+        assert_eq!(receiver.recv_timeout(Duration::from_secs(10)), Ok(3));
+    }
+
+    #[test]
+    fn purge_pool() {
+        let test_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/test");
+        let map = ProgramMap::parse("0 0 0 test_formats.sfz\n0 0 1 test.sfz", &test_dir).unwrap();
+        let options = LoadOptions::default();
+        let pool = options.pool.clone();
+        let source = ProcessorProxySource::new(8);
+        let (sender, receiver) = mpsc::channel();
+        let mut changer = ProgramChanger::new(map, options, source.get_proxy(), move |sampler, _| {
+            sender.send(sampler).unwrap();
+        });
+
+        // Files of previous instrument are purged once it is dropped.
+        changer.handle_midi_message(MidiMessage::ProgramChange(0, 0));
+        let sampler = receiver.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!(pool.stats().file_count, 2);
+        drop(sampler);
+        changer.handle_midi_message(MidiMessage::ProgramChange(0, 1));
+        let _sampler = receiver.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!(pool.stats().file_count, 1);
+    }
+}
@@ -0,0 +1,95 @@
+use std::{
+    collections::HashMap,
+    io,
+    path::{Path, PathBuf},
+};
+
+/// Maps bank select (MSB, LSB) and program numbers to instrument files.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProgramMap {
+    /// Instrument files by bank MSB, bank LSB and program.
+    programs: HashMap<(u8, u8, u8), PathBuf>,
+}
+impl ProgramMap {
+    /// Creates new, empty program map.
+    #[allow(dead_code)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads program map from config file, relative instrument paths are resolved against its directory.
+    ///
+    /// Each line holds bank MSB, bank LSB, program (0 to 127) and instrument path, e.g. `0 0 12 pianos/grand.sfz`.
+    /// Empty lines and lines starting with `#` are ignored.
+    pub fn from_file(path: &str) -> io::Result<Self> {
+        let path = Path::new(path);
+        let text = std::fs::read_to_string(path)?;
+        ProgramMap::parse(&text, path.parent().unwrap_or(Path::new("")))
+    }
+
+    /// Parses program map from config text, relative instrument paths are resolved against base directory.
+    pub fn parse(text: &str, base_dir: &Path) -> io::Result<Self> {
+        let mut map = ProgramMap::default();
+        for (line_index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            // Path is the rest of the line, so it may contain whitespace.
+            let mut rest = line;
+            let mut number = || {
+                let (number, tail) = rest.split_once(char::is_whitespace)?;
+                rest = tail.trim_start();
+                number.parse::<u8>().ok().filter(|number| *number < 128)
+            };
+            match (number(), number(), number()) {
+                (Some(msb), Some(lsb), Some(program)) if !rest.is_empty() => {
+                    map.insert((msb, lsb), program, base_dir.join(rest))
+                }
+                _ => {
+                    let message = format!("Invalid program map entry in line {}", line_index + 1);
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+                }
+            }
+        }
+        Ok(map)
+    }
+
+    /// Returns instrument file of program in bank (MSB, LSB).
+    pub fn get(&self, bank: (u8, u8), program: u8) -> Option<&Path> {
+        self.programs.get(&(bank.0, bank.1, program)).map(PathBuf::as_path)
+    }
+
+    /// Maps program in bank (MSB, LSB) to instrument file.
+    pub fn insert(&mut self, bank: (u8, u8), program: u8, file: PathBuf) {
+        self.programs.insert((bank.0, bank.1, program), file);
+    }
+
+    /// Returns number of mapped programs.
+    #[allow(dead_code)]
+    pub fn len(&self) -> usize {
+        self.programs.len()
+    }
+}
+
+/// Unit tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_map() {
+        let text = "# Bank MSB, bank LSB, program, instrument.\n\n0 0 0 piano.sfz\n0 1 5  strings/warm pad.sfz \n";
+        let map = ProgramMap::parse(text, Path::new("/instruments")).unwrap();
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get((0, 0), 0), Some(Path::new("/instruments/piano.sfz")));
+        assert_eq!(map.get((0, 1), 5), Some(Path::new("/instruments/strings/warm pad.sfz")));
+        assert_eq!(map.get((0, 0), 5), None);
+
+        // Numbers must be MIDI data bytes, path is required.
+        assert!(ProgramMap::parse("0 0 128 piano.sfz", Path::new("")).is_err());
+        assert!(ProgramMap::parse("0 0 1", Path::new("")).is_err());
+        assert!(ProgramMap::parse("piano.sfz", Path::new("")).is_err());
+    }
+}
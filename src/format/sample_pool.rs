@@ -0,0 +1,192 @@
+use crate::format::{AudioFileFormat, LoadCancelled, LoadOptions, LoadProgress, SampleStorage};
+use crate::processing::{AudioFileSound, SampleData};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, OnceLock,
+    },
+    thread,
+};
+
+/// Decoded sounds of a file, shared pool entry.
+type PoolEntry = Arc<OnceLock<Option<AudioFileSound>>>;
+
+/// Pool of decoded audio files keyed by canonical path, each file is decoded once per storage mode.
+///
+/// Clones share the pool, so sample data can be shared across regions and loaded samplers.
+#[derive(Clone, Debug, Default)]
+pub struct SamplePool {
+    entries: Arc<Mutex<HashMap<(PathBuf, SampleStorage), PoolEntry>>>,
+}
+impl SamplePool {
+    /// Creates new, empty sample pool.
+    #[allow(dead_code)]
+    pub fn new() -> Self {
+        SamplePool::default()
+    }
+
+    /// Returns sound for audio file (root note from file or 48, default settings), decodes file on first request.
+    pub fn get(&self, file_path: &str, storage: SampleStorage) -> Result<AudioFileSound, ()> {
+        let canonical_path = std::fs::canonicalize(file_path).map_err(|_| {
+            eprintln!("Sample not found: {}", file_path);
+        })?;
+
+        // Only hold pool lock to look up entry, so other files can be decoded in parallel.
+        let entry = self.entries.lock().unwrap().entry((canonical_path, storage)).or_default().clone();
+        let sound = entry.get_or_init(|| SamplePool::decode(file_path, storage));
+        sound.clone().ok_or(())
+    }
+
+    /// Decodes files in parallel on worker threads, reports progress and stops early if load is cancelled.
+    pub fn load_all(&self, files: &[(String, SampleStorage)], options: &LoadOptions) -> Result<(), LoadCancelled> {
+        let file_size = |file_path: &str| std::fs::metadata(file_path).map_or(0, |metadata| metadata.len());
+        let bytes_total = files.iter().map(|(file_path, _)| file_size(file_path)).sum();
+        let progress = Mutex::new(LoadProgress { bytes_total, files_total: files.len(), ..Default::default() });
+        let update_progress = |update: &dyn Fn(&mut LoadProgress)| {
+            let mut progress = progress.lock().unwrap();
+            update(&mut progress);
+            if let Some(sender) = &options.progress {
+                sender.send(progress.clone()).ok(); // Receiver may have been dropped.
+            }
+        };
+
+        // Workers take next file until all files are loaded or load is cancelled.
+        let next_file = AtomicUsize::new(0);
+        let worker_count = thread::available_parallelism().map_or(1, |count| count.get()).min(files.len());
+        thread::scope(|scope| {
+            for _ in 0..worker_count {
+                scope.spawn(|| {
+                    while let Some((file_path, storage)) = files.get(next_file.fetch_add(1, Ordering::Relaxed)) {
+                        if options.cancel.load(Ordering::Relaxed) {
+                            break;
+                        }
+                        update_progress(&|progress| progress.current_file = file_path.clone());
+                        self.get(file_path, *storage).ok(); // Errors are logged, sounds are skipped later.
+                        update_progress(&|progress| {
+                            progress.bytes_done += file_size(file_path);
+                            progress.current_file = file_path.clone();
+                            progress.files_done += 1;
+                        });
+                    }
+                });
+            }
+        });
+
+        match options.cancel.load(Ordering::Relaxed) {
+            true => Err(LoadCancelled),
+            false => Ok(()),
+        }
+    }
+
+    /// Removes file from pool (e.g. if it changed on disk), so it is decoded again on next request.
+    ///
+    /// Sounds that already use the file keep their sample data.
+    pub fn invalidate(&self, file_path: &Path) {
+        if let Ok(canonical_path) = std::fs::canonicalize(file_path) {
+            self.entries.lock().unwrap().retain(|(path, _), _| *path != canonical_path);
+        }
+    }
+
+    /// Removes files that are not used by any sound outside of the pool.
+    pub fn purge(&self) {
+        self.entries.lock().unwrap().retain(|_, entry| match entry.get() {
+            Some(Some(sound)) => Arc::strong_count(sound.sample_data()) > 1,
+            _ => false,
+        });
+    }
+
+    /// Returns memory usage statistics.
+    pub fn stats(&self) -> SamplePoolStats {
+        let mut stats = SamplePoolStats::default();
+        for sound in self.entries.lock().unwrap().values().filter_map(|entry| entry.get().and_then(Option::as_ref)) {
+            let sample_data = sound.sample_data();
+            stats.file_count += 1;
+            stats.sound_count += Arc::strong_count(sample_data) - 1; // Without pool.
+            match sample_data.as_ref() {
+                SampleData::Buffer(_) => stats.decoded_bytes += sample_data.byte_size(),
+                SampleData::Mapped(_) => stats.mapped_bytes += sample_data.byte_size(),
+            }
+        }
+        stats
+    }
+
+    /// Decodes audio file according to storage mode.
+    fn decode(file_path: &str, storage: SampleStorage) -> Option<AudioFileSound> {
+        let format = match AudioFileFormat::from_file(file_path) {
+            Some(format) => format,
+            None => {
+                eprintln!("Unsupported audio file format: {}", file_path);
+                return None;
+            }
+        };
+        let midi_region = (48, 0, 127, 0, 127);
+        let adsr = (0.001, 0.0, 0.0, 0.03);
+        let sound = match (storage, format) {
+            (SampleStorage::Stream { preload_frames }, AudioFileFormat::Wav) => {
+                AudioFileSound::from_wav_streamed(file_path, preload_frames, midi_region, adsr).or(Err(()))
+            }
+            (SampleStorage::Mapped, AudioFileFormat::Wav) => {
+                // Decode into memory if encoding is not supported for mapping.
+                AudioFileSound::from_wav_mapped(file_path, midi_region, adsr)
+                    .or_else(|_| format.decode_file(file_path, midi_region, adsr))
+            }
+            _ => format.decode_file(file_path, midi_region, adsr),
+        };
+        sound.map_err(|_| eprintln!("Failed to load sample: {}", file_path)).ok()
+    }
+}
+
+/// Memory usage statistics of a sample pool.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SamplePoolStats {
+    /// Bytes of decoded sample data held in memory.
+    pub decoded_bytes: usize,
+
+    /// Decoded or mapped files.
+    pub file_count: usize,
+
+    /// Bytes of memory-mapped files (paged in by the OS on demand).
+    pub mapped_bytes: usize,
+
+    /// Sounds sharing sample data of pool.
+    pub sound_count: usize,
+}
+
+/// Unit tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::{LoadOptions, SfzLoader};
+
+    #[test]
+    fn shares_samples() {
+        let test_file =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/test/test.sfz").to_str().unwrap().to_string();
+        let options = LoadOptions::default();
+        let sampler_1 = SfzLoader::from_file_with_options(&test_file, &options).unwrap();
+        let sampler_2 = SfzLoader::from_file_with_options(&test_file, &options).unwrap();
+
+        // Three regions of two samplers share one decoded file (48000 stereo frames plus 32 padding frames each side).
+        let sounds_1 = sampler_1.get_sounds();
+        let sounds_2 = sampler_2.get_sounds();
+        assert!(Arc::ptr_eq(sounds_1[0].sample_data(), sounds_1[1].sample_data()));
+        assert!(Arc::ptr_eq(sounds_1[0].sample_data(), sounds_2[2].sample_data()));
+        assert_eq!(sounds_1[0].midi_region, (29, 24, 32, 1, 47)); // Region settings are not shared.
+        assert_eq!(sounds_1[1].midi_region, (24, 34, 48, 48, 72));
+        let stats = options.pool.stats();
+        assert_eq!(
+            stats,
+            SamplePoolStats { decoded_bytes: 48064 * 2 * 4, file_count: 1, mapped_bytes: 0, sound_count: 6 }
+        );
+
+        // Files are kept while in use.
+        options.pool.purge();
+        assert_eq!(options.pool.stats().file_count, 1);
+        drop(sampler_1);
+        drop(sampler_2);
+        options.pool.purge();
+        assert_eq!(options.pool.stats(), SamplePoolStats::default());
+    }
+}
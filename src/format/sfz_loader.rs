@@ -1,34 +1,106 @@
-use crate::processing::{AudioFileSound, AudioFileVoice, Sampler};
-use std::path::{Path, PathBuf};
+use crate::format::{LoadError, LoadOptions, LoadReport, SampleStorage};
+use crate::processing::{
+    AudioFileSound, AudioFileVoice, ExpressionModulation, Interpolation, LoopMode, SameNoteBehavior, SampleStreamer,
+    Sampler, DEFAULT_POLYPHONY, DEFAULT_RESERVED_VOICES,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+/// Maximum nesting depth of included files, guards against include cycles.
+const MAX_INCLUDE_DEPTH: usize = 16;
 
 /// Loader for SFZ-based samplers.
 pub struct SfzLoader {}
 impl SfzLoader {
     /// Creates sampler from SFZ file.
+    #[allow(dead_code)]
     pub fn from_file(path: &str) -> Sampler<AudioFileSound, AudioFileVoice> {
-        // Parse file and create sampler.
-        let instrument = sofiza::Instrument::from_file(&Path::new(path)).expect("Failed to load SFZ file.");
+        SfzLoader::from_file_with_options(path, &LoadOptions::default()).expect("Failed to load SFZ file.")
+    }
+
+    /// Creates sampler from SFZ file with load options, samples are decoded in parallel.
+    #[allow(dead_code)]
+    pub fn from_file_with_options(
+        path: &str,
+        options: &LoadOptions,
+    ) -> Result<Sampler<AudioFileSound, AudioFileVoice>, LoadError> {
+        SfzLoader::from_file_with_report(path, options).map(|(sampler, _)| sampler)
+    }
+
+    /// Creates sampler from SFZ file with load options, also returns a report (e.g. which files to watch).
+    ///
+    /// Fails if the SFZ file can't be read or parsed, missing includes and samples are only reported.
+    pub fn from_file_with_report(
+        path: &str,
+        options: &LoadOptions,
+    ) -> Result<(Sampler<AudioFileSound, AudioFileVoice>, LoadReport), LoadError> {
+        let start_time = Instant::now();
+        let mut report = LoadReport::default();
+
+        // Expand includes, parse file and create sampler.
+        let path = Path::new(path);
+        let text = SfzLoader::read_file(path, &mut report, 0)?;
+        let instrument = SfzLoader::parse(&text, path.parent().unwrap_or(Path::new("")))?;
+        let (global_opcodes, raw_opcodes) = SfzLoader::raw_opcodes(&text);
         let mut sampler = Sampler::new();
+        options.configure_sampler(&mut sampler);
 
-        // TODO: Add voices based on polyphony opcode (defaults to 64).
-        for _ in 0..64 {
-            sampler.add_voice(AudioFileVoice::new());
+        // Add voices based on polyphony option or opcode, plus voices for fade-out of stolen voices.
+        let polyphony = options.polyphony.or_else(|| global_opcodes.get("polyphony")?.parse().ok());
+        let streamer = match options.storage {
+            SampleStorage::Stream { .. } => Some(SampleStreamer::new()),
+            SampleStorage::Memory | SampleStorage::Mapped => None,
+        };
+        for _ in 0..polyphony.unwrap_or(DEFAULT_POLYPHONY).max(1) + DEFAULT_RESERVED_VOICES {
+            sampler.add_voice(match &streamer {
+                Some(streamer) => AudioFileVoice::with_streamer(streamer),
+                None => AudioFileVoice::new(),
+            });
         }
+        sampler.set_reserved_voices(DEFAULT_RESERVED_VOICES);
 
-        // Add sounds.
-        for region in instrument.regions {
-            let mut sound_builder = AudioFileSoundBuilder::new(instrument.default_path.clone());
+        // Apply opcodes according to precedence, opcodes not parsed by sofiza are applied from raw text.
+        let sound_builders: Vec<AudioFileSoundBuilder> = instrument
+            .regions
+            .iter()
+            .enumerate()
+            .map(|(index, region)| {
+                let mut sound_builder = AudioFileSoundBuilder::new(instrument.default_path.clone());
+                instrument.global.values().for_each(|opcode| sound_builder.apply(opcode));
+                if let Some(group) = region.group {
+                    instrument.groups[group].opcodes.values().for_each(|opcode| sound_builder.apply(opcode));
+                }
+                region.opcodes.values().for_each(|opcode| sound_builder.apply(opcode));
+                if let Some(opcodes) = raw_opcodes.get(index) {
+                    opcodes.iter().for_each(|(name, value)| sound_builder.apply_raw(name, value));
+                }
+                sound_builder
+            })
+            .collect();
 
-            // Apply opcodes according to precedence.
-            instrument.global.values().for_each(|opcode| sound_builder.apply(opcode));
-            if let Some(group) = region.group {
-                instrument.groups[group].opcodes.values().for_each(|opcode| sound_builder.apply(opcode));
-            }
-            region.opcodes.values().for_each(|opcode| sound_builder.apply(opcode));
+        // Decode each sample file once, in parallel.
+        let mut unique_files = HashSet::new();
+        let files: Vec<(String, SampleStorage)> = sound_builders
+            .iter()
+            .map(|sound_builder| (sound_builder.file_path.clone(), sound_builder.storage(options)))
+            .filter(|file| unique_files.insert(file.clone()))
+            .collect();
+        options.pool.load_all(&files, options)?;
+        let mut unique_paths = HashSet::new();
+        report.sample_files = files
+            .iter()
+            .filter(|(file_path, _)| !file_path.is_empty() && unique_paths.insert(file_path))
+            .map(|(file_path, _)| PathBuf::from(file_path))
+            .collect();
 
-            // Add if valid.
-            if let Ok(sound) = sound_builder.build() {
-                sampler.add_sound(sound);
+        // Add sounds if valid, samples are taken from pool.
+        for (region, sound_builder) in instrument.regions.iter().zip(sound_builders) {
+            match sound_builder.build(options) {
+                Ok(sound) => sampler.add_sound(sound),
+                Err(_) => report.errors.push(format!("Failed to load sample: {}", sound_builder.file_path)),
             }
 
             // Debug logging.
@@ -36,35 +108,139 @@ impl SfzLoader {
             println!("[SfzLoader] Added {:?}", region);
         }
 
-        sampler
+        // Debug logging.
+        #[cfg(debug_assertions)]
+        println!("[SfzLoader] Sample pool: {:?}", options.pool.stats());
+
+        report.duration = start_time.elapsed();
+        report.pool_stats = options.pool.stats();
+        report.region_count = instrument.regions.len();
+        report.sound_count = sampler.get_sounds().len();
+        Ok((sampler, report))
+    }
+
+    /// Parses expanded SFZ text, sofiza panics on some malformed files (e.g. opcodes before the first header).
+    fn parse(text: &str, sfz_dir: &Path) -> Result<sofiza::Instrument, LoadError> {
+        match std::panic::catch_unwind(|| sofiza::Instrument::from_sfz(text, sfz_dir)) {
+            Ok(Ok(instrument)) => Ok(instrument),
+            Ok(Err(error)) => Err(LoadError::Parse(error.to_string())),
+            Err(_) => Err(LoadError::Parse(String::from("Malformed SFZ file"))),
+        }
+    }
+
+    /// Returns global opcodes and opcodes of each region as raw strings, regions include inherited global, master and
+    /// group opcodes.
+    fn raw_opcodes(text: &str) -> (HashMap<String, String>, Vec<HashMap<String, String>>) {
+        let mut regions = Vec::new();
+        let mut scopes: [HashMap<String, String>; 4] = Default::default(); // Global, master, group, region.
+        let mut scope = Some(0);
+        let mut push_region = |scopes: &[HashMap<String, String>; 4], scope| {
+            if scope == Some(3) {
+                regions.push(scopes.iter().flatten().map(|(name, value)| (name.clone(), value.clone())).collect());
+            }
+        };
+        for line in text.lines() {
+            let line = line.split("//").next().unwrap_or_default();
+            for mut token in line.split_whitespace() {
+                // Headers might be followed by an opcode without whitespace.
+                while let Some((header, rest)) = token.strip_prefix('<').and_then(|token| token.split_once('>')) {
+                    push_region(&scopes, scope);
+                    scope = match header {
+                        "global" => Some(0),
+                        "master" => Some(1),
+                        "group" => Some(2),
+                        "region" => Some(3),
+                        _ => None, // Opcodes of other headers (e.g. control) don't apply to regions.
+                    };
+                    if let Some(scope) = scope {
+                        scopes[scope..].iter_mut().for_each(HashMap::clear);
+                    }
+                    token = rest;
+                }
+                if let (Some(scope), Some((name, value))) = (scope, token.split_once('=')) {
+                    scopes[scope].insert(name.to_string(), value.to_string());
+                }
+            }
+        }
+        push_region(&scopes, scope);
+        (std::mem::take(&mut scopes[0]), regions)
+    }
+
+    /// Reads SFZ file and expands `#include "file"` lines recursively, included paths are relative to SFZ file.
+    ///
+    /// Read files are added to report, so they can be watched for changes. Missing includes are only reported.
+    fn read_file(path: &Path, report: &mut LoadReport, depth: usize) -> std::io::Result<String> {
+        report.source_files.push(path.to_path_buf());
+        let text = std::fs::read_to_string(path)?;
+
+        let root_dir = report.source_files[0].parent().unwrap_or(Path::new("")).to_path_buf();
+        let mut expanded_text = String::with_capacity(text.len());
+        for line in text.lines() {
+            let include = line.trim().strip_prefix("#include").and_then(|include| include.split('"').nth(1));
+            match include {
+                Some(_) if depth >= MAX_INCLUDE_DEPTH => {
+                    report.errors.push(format!("Include depth exceeded in {}", path.display()))
+                }
+                Some(include) => {
+                    let include_path = root_dir.join(include);
+                    match SfzLoader::read_file(&include_path, report, depth + 1) {
+                        Ok(text) => expanded_text.push_str(&text),
+                        Err(error) => {
+                            report.errors.push(format!("Failed to read {}: {}", include_path.display(), error))
+                        }
+                    }
+                }
+                None => expanded_text.push_str(line),
+            }
+            expanded_text.push('\n');
+        }
+        Ok(expanded_text)
     }
 }
 
 /// Audio file sound builder.
 struct AudioFileSoundBuilder {
     attack: f32,
+    bend_down: f32,
+    bend_up: f32,
     default_path: PathBuf,
+    expression: ExpressionModulation,
     file_path: String,
     high_note: u8,
     high_velocity: u8,
+    interpolation: Option<Interpolation>,
     low_note: u8,
+    loop_mode: Option<LoopMode>,
     low_velocity: u8,
+    note_polyphony: Option<u8>,
+    note_selfmask: bool,
+    off_mode_fast: bool,
+    offset: usize,
     release: f32,
-    root_note: u8,
+    root_note: Option<u8>,
 }
 impl AudioFileSoundBuilder {
     /// Creates new sound builder.
     fn new(default_path: PathBuf) -> AudioFileSoundBuilder {
         AudioFileSoundBuilder {
             attack: 0.001,
+            bend_down: -200.0,
+            bend_up: 200.0,
             default_path,
+            expression: ExpressionModulation::default(),
             file_path: String::from(""),
             high_note: 127,
             high_velocity: 127,
+            interpolation: None,
             low_note: 0,
+            loop_mode: None,
             low_velocity: 0,
+            note_polyphony: None,
+            note_selfmask: true,
+            off_mode_fast: false,
+            offset: 0,
             release: 0.03,
-            root_note: 48,
+            root_note: None,
         }
     }
 
@@ -73,11 +249,21 @@ impl AudioFileSoundBuilder {
         match opcode {
             sofiza::Opcode::ampeg_attack(attack) => self.attack = *attack,
             sofiza::Opcode::ampeg_release(release) => self.release = *release,
+            sofiza::Opcode::bend_down(cents) => self.bend_down = *cents as f32,
+            sofiza::Opcode::bend_up(cents) => self.bend_up = *cents as f32,
             sofiza::Opcode::hikey(note) => self.high_note = *note,
             sofiza::Opcode::hivel(velocity) => self.high_velocity = *velocity,
             sofiza::Opcode::lokey(note) => self.low_note = *note,
+            sofiza::Opcode::loop_mode(mode) => {
+                self.loop_mode = Some(match mode {
+                    sofiza::loop_mode::loop_continuous => LoopMode::LoopContinuous,
+                    sofiza::loop_mode::loop_sustain => LoopMode::LoopSustain,
+                    _ => LoopMode::NoLoop, // One shot is not supported yet.
+                })
+            }
             sofiza::Opcode::lovel(velocity) => self.low_velocity = *velocity,
-            sofiza::Opcode::pitch_keycenter(note) => self.root_note = *note,
+            sofiza::Opcode::offset(offset) => self.offset = *offset as usize,
+            sofiza::Opcode::pitch_keycenter(note) => self.root_note = Some(*note),
             sofiza::Opcode::sample(path) => {
                 self.file_path = String::from(self.default_path.join(path).to_str().unwrap())
             }
@@ -85,20 +271,91 @@ impl AudioFileSoundBuilder {
         }
     }
 
-    /// Builds sound.
-    fn build(&self) -> Result<AudioFileSound, ()> {
-        if self.file_path.ends_with(".wav") {
-            let midi_region = (self.root_note, self.low_note, self.high_note, self.low_velocity, self.high_velocity);
-            let adsr = (self.attack, 0.0, 0.0, self.release);
-            let sound = AudioFileSound::from_wav(&self.file_path, midi_region, adsr);
-            if sound.is_err() {
-                eprintln!("Failed to load sample: {}", self.file_path);
+    /// Applies opcode that is not parsed by sofiza, invalid values are ignored.
+    fn apply_raw(&mut self, name: &str, value: &str) {
+        match name {
+            // Expression controllers, 129 is channel pressure, 130 is polyphonic key pressure and 74 is timbre.
+            "amplitude_oncc129" | "amplitude_oncc130" | "amplitude_oncc74" => {
+                if let Ok(amount) = value.parse::<f32>() {
+                    let amount = amount.clamp(-100.0, 100.0);
+                    match name {
+                        "amplitude_oncc129" => self.expression.amplitude.0 = amount,
+                        "amplitude_oncc130" => self.expression.amplitude.1 = amount,
+                        _ => self.expression.amplitude.2 = amount,
+                    }
+                }
             }
-            sound.or(Err(()))
-        } else {
-            eprintln!("Unsupported audio file format: {}", self.file_path);
-            Err(())
+            "pitch_oncc129" | "pitch_oncc130" | "pitch_oncc74" => {
+                if let Ok(amount) = value.parse::<f32>() {
+                    let amount = amount.clamp(-9600.0, 9600.0);
+                    match name {
+                        "pitch_oncc129" => self.expression.pitch.0 = amount,
+                        "pitch_oncc130" => self.expression.pitch.1 = amount,
+                        _ => self.expression.pitch.2 = amount,
+                    }
+                }
+            }
+            // Same note behavior, notes are released by default (as if note_polyphony=1 without self mask).
+            "note_polyphony" => {
+                if let Ok(note_polyphony) = value.parse::<u8>() {
+                    self.note_polyphony = Some(note_polyphony.max(1));
+                }
+            }
+            "note_selfmask" => match value {
+                "on" => self.note_selfmask = true,
+                "off" => self.note_selfmask = false,
+                _ => (),
+            },
+            "off_mode" => match value {
+                "fast" => self.off_mode_fast = true,
+                "normal" => self.off_mode_fast = false,
+                _ => (),
+            },
+            // Sofiza drops negative values.
+            "bend_down" => {
+                if let Ok(cents) = value.parse::<i16>() {
+                    self.bend_down = cents.clamp(-9600, 9600) as f32;
+                }
+            }
+            "sample_quality" => {
+                if let Ok(sample_quality) = value.parse() {
+                    self.interpolation = Some(Interpolation::from_sample_quality(sample_quality));
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Returns sample storage, loops are read repeatedly so they are kept in memory instead of streamed.
+    fn storage(&self, options: &LoadOptions) -> SampleStorage {
+        let looping = self.loop_mode.is_some_and(|loop_mode| loop_mode != LoopMode::NoLoop);
+        match options.storage {
+            SampleStorage::Stream { .. } if looping => SampleStorage::Memory,
+            storage => storage,
+        }
+    }
+
+    /// Builds sound, sample data is shared via sample pool.
+    fn build(&self, options: &LoadOptions) -> Result<AudioFileSound, ()> {
+        let mut sound = options.pool.get(&self.file_path, self.storage(options))?;
+
+        // Opcodes take precedence over root note and loop stored in audio file.
+        let root_note = self.root_note.unwrap_or(sound.midi_region.0);
+        sound.midi_region = (root_note, self.low_note, self.high_note, self.low_velocity, self.high_velocity);
+        sound.adsr = (self.attack, 0.0, 0.0, self.release);
+        (sound.bend_up, sound.bend_down) = (self.bend_up, self.bend_down);
+        sound.expression = self.expression;
+        sound.interpolation = self.interpolation.unwrap_or(options.interpolation);
+        if let Some(loop_mode) = self.loop_mode {
+            sound.loop_mode = loop_mode;
         }
+        sound.offset = self.offset.min(sound.duration_samples);
+        sound.same_note = SameNoteBehavior {
+            cut: self.off_mode_fast,
+            max_voices: Some(self.note_polyphony.unwrap_or(1)),
+            self_mask: self.note_polyphony.is_some() && self.note_selfmask,
+        };
+        Ok(sound)
     }
 }
 
@@ -106,6 +363,8 @@ impl AudioFileSoundBuilder {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::base::AudioProcessor;
+    use crate::format::LoadProgress;
 
     #[test]
     fn from_file() {
@@ -121,4 +380,138 @@ mod tests {
         assert_eq!(sounds[1].midi_region, (24, 34, 48, 48, 72));
         assert_eq!(sounds[2].midi_region, (24, 49, 56, 48, 72));
     }
+
+    #[test]
+    fn polyphony() {
+        let test_file =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/test/test_polyphony.sfz").to_str().unwrap().to_string();
+        let sampler = SfzLoader::from_file(&test_file);
+        assert_eq!(sampler.get_voices().len(), 16 + DEFAULT_RESERVED_VOICES);
+
+        // Polyphony option overrides opcode.
+        let options = LoadOptions { polyphony: Some(8), ..Default::default() };
+        let sampler = SfzLoader::from_file_with_options(&test_file, &options).unwrap();
+        assert_eq!(sampler.get_voices().len(), 8 + DEFAULT_RESERVED_VOICES);
+    }
+
+    #[test]
+    fn include_files() {
+        let test_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/test");
+        let test_file = test_dir.join("test_include.sfz").to_str().unwrap().to_string();
+        let options = LoadOptions { interpolation: Interpolation::Hermite, ..Default::default() };
+        let (sampler, report) = SfzLoader::from_file_with_report(&test_file, &options).unwrap();
+        let sounds = sampler.get_sounds();
+
+        // Regions of included file come first, missing sample is reported.
+        assert_eq!(sounds.len(), 4);
+        assert_eq!(sounds[0].midi_region, (29, 24, 32, 1, 47));
+        assert_eq!(sounds[3].midi_region, (60, 60, 72, 0, 127));
+
+        // Region sample quality takes precedence over group and default interpolation.
+        assert_eq!(sounds[0].interpolation, Interpolation::Hermite);
+        assert_eq!(sounds[3].interpolation, Interpolation::Sinc);
+        assert_eq!((sounds[0].bend_up, sounds[0].bend_down), (200.0, -200.0));
+        assert_eq!((sounds[3].bend_up, sounds[3].bend_down), (1200.0, -1200.0));
+        assert_eq!(sounds[0].expression, ExpressionModulation::default());
+        let expression = ExpressionModulation { amplitude: (0.0, -50.0, 0.0), pitch: (100.0, 0.0, 25.0) };
+        assert_eq!(sounds[3].expression, expression);
+        assert_eq!(sounds[0].same_note, SameNoteBehavior::default());
+        let same_note = SameNoteBehavior { cut: true, max_voices: Some(4), self_mask: false };
+        assert_eq!(sounds[3].same_note, same_note);
+        assert_eq!(report.region_count, 5);
+        assert_eq!(report.sound_count, 4);
+        assert_eq!(report.source_files, vec![test_dir.join("test_include.sfz"), test_dir.join("test.sfz")]);
+        assert_eq!(report.sample_files, vec![test_dir.join("test_sine.wav"), test_dir.join("missing.wav")]);
+        assert_eq!(report.errors.len(), 1);
+    }
+
+    #[test]
+    fn resample_samples() {
+        let test_file =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/test/test.sfz").to_str().unwrap().to_string();
+        let options = LoadOptions { resample: true, ..Default::default() };
+        let mut sampler = SfzLoader::from_file_with_options(&test_file, &options).unwrap();
+        assert_eq!(sampler.get_sounds()[0].sample_rate, 48000.0);
+
+        // Sounds are converted on reset and still share sample data.
+        sampler.reset(44100.0, 512);
+        let sounds = sampler.get_sounds();
+        assert_eq!(sounds[0].sample_rate, 44100.0);
+        assert_eq!(sounds[0].duration_samples, 44100);
+        assert_eq!(sounds[0].loop_region, (0, 44100));
+        assert!(std::sync::Arc::ptr_eq(sounds[0].sample_data(), sounds[2].sample_data()));
+    }
+
+    #[test]
+    fn stream_samples() {
+        let test_file =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/test/test.sfz").to_str().unwrap().to_string();
+        let options = LoadOptions { storage: SampleStorage::Stream { preload_frames: 4096 }, ..Default::default() };
+        let sampler = SfzLoader::from_file_with_options(&test_file, &options).unwrap();
+        let sounds = sampler.get_sounds();
+
+        assert_eq!(sounds.len(), 3);
+        assert_eq!(sounds[0].duration_samples, 48000);
+        assert_eq!(sounds[0].preloaded_frames(), 4096);
+        assert!(sounds[0].stream_source().is_some());
+    }
+
+    #[test]
+    fn map_samples() {
+        let test_file =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/test/test.sfz").to_str().unwrap().to_string();
+        let options = LoadOptions { storage: SampleStorage::Mapped, ..Default::default() };
+        let sampler = SfzLoader::from_file_with_options(&test_file, &options).unwrap();
+        let sounds = sampler.get_sounds();
+        let reference = SfzLoader::from_file(&test_file);
+
+        assert_eq!(sounds.len(), 3);
+        assert_eq!(sounds[0].duration_samples, 48000);
+        assert_eq!(sounds[0].midi_region, (29, 24, 32, 1, 47));
+        assert_eq!(sounds[0].get_value(100.5), reference.get_sounds()[0].get_value(100.5));
+    }
+
+    #[test]
+    fn report_progress() {
+        let test_file =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/test/test.sfz").to_str().unwrap().to_string();
+        let sample_file =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/test/test_sine.wav").to_str().unwrap().to_string();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let options = LoadOptions { progress: Some(sender), ..Default::default() };
+        SfzLoader::from_file_with_options(&test_file, &options).unwrap();
+        drop(options);
+
+        // Three regions share one sample file, progress is sent on start and finish.
+        let progress: Vec<LoadProgress> = receiver.iter().collect();
+        let bytes_total = std::fs::metadata(&sample_file).unwrap().len();
+        assert_eq!(progress.len(), 2);
+        assert_eq!(progress[0].files_done, 0);
+        assert_eq!(progress[0].files_total, 1);
+        assert_eq!(progress[0].bytes_total, bytes_total);
+        assert!(progress[0].current_file.ends_with("test_sine.wav"));
+        assert_eq!(progress[1].files_done, 1);
+        assert_eq!(progress[1].bytes_done, bytes_total);
+    }
+
+    #[test]
+    fn cancel_loading() {
+        let test_file =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/test/test.sfz").to_str().unwrap().to_string();
+        let options = LoadOptions::default();
+        options.cancel.store(true, std::sync::atomic::Ordering::Relaxed);
+        let result = SfzLoader::from_file_with_options(&test_file, &options);
+        assert!(matches!(result, Err(LoadError::Cancelled)));
+        assert_eq!(options.pool.stats().file_count, 0);
+    }
+
+    #[test]
+    fn load_errors() {
+        let test_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/test");
+        let missing_file = test_dir.join("missing.sfz").to_str().unwrap().to_string();
+        let invalid_file = test_dir.join("test_invalid.sfz").to_str().unwrap().to_string();
+        let options = LoadOptions::default();
+        assert!(matches!(SfzLoader::from_file_with_options(&missing_file, &options), Err(LoadError::Io(_))));
+        assert!(matches!(SfzLoader::from_file_with_options(&invalid_file, &options), Err(LoadError::Parse(_))));
+    }
 }
@@ -0,0 +1,174 @@
+use crate::format::{LoadError, LoadOptions, LoadReport, SampleStorage, SfzLoader};
+use crate::processing::{AudioFileSound, AudioFileVoice, Sampler};
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, SystemTime},
+};
+
+/// Interval in which watched files are checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Modification time and size of a file, [None] if file does not exist.
+type FileState = Option<(SystemTime, u64)>;
+
+/// Watches an SFZ file, its included files and samples, and reloads the instrument whenever one of them changes.
+///
+/// Files are polled, so this works the same on every platform and with editors that replace files on save.
+pub struct SfzWatcher {
+    /// Keeps watcher thread running, cleared on drop.
+    running: Arc<AtomicBool>,
+
+    /// Watcher thread.
+    thread: Option<thread::JoinHandle<()>>,
+}
+impl SfzWatcher {
+    /// Starts watching files of an instrument loaded from path, reloaded samplers are passed to callback.
+    ///
+    /// Report of the initial load determines which files are watched, each reload updates them and prints its report.
+    /// Samples are edited while loaded, so [SampleStorage::Mapped] is not supported.
+    pub fn new<F>(path: &str, options: LoadOptions, report: &LoadReport, on_reload: F) -> Result<Self, LoadError>
+    where
+        F: FnMut(Sampler<AudioFileSound, AudioFileVoice>, LoadReport) + Send + 'static,
+    {
+        if options.storage == SampleStorage::Mapped {
+            return Err(LoadError::Unsupported(String::from("Mapped samples can't be watched.")));
+        }
+        let running = Arc::new(AtomicBool::new(true));
+        let thread_running = running.clone();
+        let path = String::from(path);
+        let files = report.files().cloned().collect();
+        let thread = thread::Builder::new()
+            .name(String::from("sfz-watcher"))
+            .spawn(move || SfzWatcher::run(thread_running, path, options, files, on_reload))
+            .expect("Failed to start SFZ watcher thread.");
+        Ok(SfzWatcher { running, thread: Some(thread) })
+    }
+
+    /// Watcher thread, reloads instrument once changed files have settled (e.g. editor finished saving).
+    ///
+    /// If reloading fails (e.g. file saved mid-edit), previous instrument keeps playing until files change again.
+    fn run<F>(running: Arc<AtomicBool>, path: String, options: LoadOptions, files: Vec<PathBuf>, mut on_reload: F)
+    where
+        F: FnMut(Sampler<AudioFileSound, AudioFileVoice>, LoadReport),
+    {
+        let mut file_states = SfzWatcher::file_states(files);
+        while running.load(Ordering::Relaxed) {
+            thread::sleep(POLL_INTERVAL);
+            let current_states = SfzWatcher::file_states(file_states.keys().cloned());
+            if current_states == file_states {
+                continue;
+            }
+
+            // Wait until files stop changing.
+            let mut settled_states = current_states;
+            loop {
+                thread::sleep(POLL_INTERVAL);
+                let current_states = SfzWatcher::file_states(settled_states.keys().cloned());
+                if current_states == settled_states {
+                    break;
+                }
+                settled_states = current_states;
+            }
+
+            // Changed samples have to be decoded again, unchanged samples are taken from pool.
+            for (file, state) in settled_states.iter() {
+                if file_states.get(file) != Some(state) {
+                    options.pool.invalidate(file);
+                }
+            }
+            file_states = settled_states;
+
+            match SfzLoader::from_file_with_report(&path, &options) {
+                Ok((sampler, report)) => {
+                    println!("[SfzWatcher] {}", report);
+                    file_states = SfzWatcher::file_states(report.files().cloned());
+                    on_reload(sampler, report);
+                }
+                Err(LoadError::Cancelled) => println!("[SfzWatcher] Reload was cancelled."),
+                Err(error) => eprintln!("[SfzWatcher] Reload failed, keeping previous instrument: {}", error),
+            }
+        }
+    }
+
+    /// Returns current state of files.
+    fn file_states(files: impl IntoIterator<Item = PathBuf>) -> HashMap<PathBuf, FileState> {
+        files
+            .into_iter()
+            .map(|file| {
+                let metadata = std::fs::metadata(&file).ok();
+                let state = metadata.and_then(|metadata| Some((metadata.modified().ok()?, metadata.len())));
+                (file, state)
+            })
+            .collect()
+    }
+}
+impl Drop for SfzWatcher {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            thread.join().ok();
+        }
+    }
+}
+
+/// Unit tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn reload_on_change() {
+        // Work on copies, so fixtures are not modified.
+        let test_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/test");
+        let temp_dir = std::env::temp_dir().join(format!("sfz-watcher-{}", std::process::id()));
+        std::fs::create_dir_all(&temp_dir).unwrap();
+        for file in ["test.sfz", "test_include.sfz", "test_sine.wav"] {
+            std::fs::copy(test_dir.join(file), temp_dir.join(file)).unwrap();
+        }
+        let test_file = temp_dir.join("test_include.sfz").to_str().unwrap().to_string();
+
+        let options = LoadOptions::default();
+        let (_, report) = SfzLoader::from_file_with_report(&test_file, &options).unwrap();
+        let (sender, receiver) = mpsc::channel();
+        let watcher = SfzWatcher::new(&test_file, options, &report, move |sampler, report| {
+            sender.send((sampler.get_sounds().len(), report)).unwrap();
+        })
+        .unwrap();
+
+        // Changing included file reloads instrument.
+        thread::sleep(POLL_INTERVAL * 2);
+        let text = std::fs::read_to_string(temp_dir.join("test.sfz")).unwrap();
+        std::fs::write(temp_dir.join("test.sfz"), text.replace("<region> sample=test_sine.wav lokey=49", "")).unwrap();
+        let (sound_count, report) = receiver.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!(sound_count, 3);
+        assert_eq!(report.source_files.len(), 2);
+
+        // Broken file keeps previous instrument, watching continues.
+        let text = std::fs::read_to_string(temp_dir.join("test_include.sfz")).unwrap();
+        std::fs::write(temp_dir.join("test_include.sfz"), format!("lokey=24\n{}", text)).unwrap();
+        assert!(receiver.recv_timeout(POLL_INTERVAL * 8).is_err());
+        std::fs::write(temp_dir.join("test_include.sfz"), text).unwrap();
+        let (sound_count, _) = receiver.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!(sound_count, 3);
+
+        drop(watcher);
+        std::fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    #[test]
+    fn reject_mapped_samples() {
+        let test_file =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/test/test.sfz").to_str().unwrap().to_string();
+        let options = LoadOptions { storage: SampleStorage::Mapped, ..Default::default() };
+        let (_, report) = SfzLoader::from_file_with_report(&test_file, &options).unwrap();
+        let watcher = SfzWatcher::new(&test_file, options, &report, |_, _| ());
+        assert!(matches!(watcher, Err(LoadError::Unsupported(_))));
+    }
+}
@@ -6,12 +6,147 @@ mod processing;
 #[cfg(test)]
 mod test;
 
+use format::SampleStorage;
+use processing::{GlideCurve, Interpolation, NotePriority, PortamentoMode, StealPolicy};
+
+/// Frames loaded into memory per sample with `--storage=stream`, the rest is streamed from disk.
+const STREAM_PRELOAD_FRAMES: usize = 8192;
+
 fn main() {
-    let path = "samples/rhodes.sfz";
-    let sampler = format::SfzLoader::from_file(path);
-    let (processor, proxy) = engine::CpalProcessor::new(Box::new(sampler));
-    let _audio_engine = engine::CpalAudioEngine::new(processor);
-    let _midi_engine = engine::MidirMidiEngine::new(proxy);
+    // Usage: sampler [path] [--watch] [--programs=file] [--mono] [--legato] [--priority=last|lowest|highest]
+    //     [--polyphony=voices] [--steal=oldest|quietest|releasing|same-note] [--protect-highest] [--protect-lowest]
+    //     [--mpe=lower_members[,upper_members]] [--portamento-mode=always|legato] [--portamento-curve=time|rate]
+    //     [--storage=memory|stream|mapped] [--interpolation=linear|hermite|sinc] [--resample]
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let path = args.iter().find(|arg| !arg.starts_with("--")).map_or("samples/rhodes.sfz", String::as_str);
+    let watch = args.iter().any(|arg| arg == "--watch");
+    let programs = args.iter().find_map(|arg| arg.strip_prefix("--programs="));
+    if watch && programs.is_some() {
+        // Watcher would swap its instrument back in over the one selected by program change.
+        eprintln!("--watch and --programs can't be combined.");
+        std::process::exit(1);
+    }
+    let multisample = std::path::Path::new(path).extension().is_some_and(|extension| extension == "multisample");
+    if multisample && (watch || programs.is_some()) {
+        // Watcher and program map only handle SFZ files.
+        eprintln!("--watch and --programs require an SFZ file.");
+        std::process::exit(1);
+    }
+
+    // Loading and playing options apply to every loaded instrument.
+    let interpolations =
+        [("hermite", Interpolation::Hermite), ("linear", Interpolation::Linear), ("sinc", Interpolation::Sinc)];
+    let portamento_curves = [("rate", GlideCurve::ConstantRate), ("time", GlideCurve::ConstantTime)];
+    let portamento_modes = [("always", PortamentoMode::Always), ("legato", PortamentoMode::LegatoOnly)];
+    let priorities =
+        [("highest", NotePriority::Highest), ("last", NotePriority::Last), ("lowest", NotePriority::Lowest)];
+    let steal_policies = [
+        ("oldest", StealPolicy::Oldest),
+        ("quietest", StealPolicy::Quietest),
+        ("releasing", StealPolicy::ReleasingFirst),
+        ("same-note", StealPolicy::SameNoteFirst),
+    ];
+    let storages = [
+        ("mapped", SampleStorage::Mapped),
+        ("memory", SampleStorage::Memory),
+        ("stream", SampleStorage::Stream { preload_frames: STREAM_PRELOAD_FRAMES }),
+    ];
+    let polyphony = args.iter().find_map(|arg| arg.strip_prefix("--polyphony=")).map(|polyphony| {
+        polyphony.parse().unwrap_or_else(|_| {
+            eprintln!("Invalid polyphony: {}", polyphony);
+            std::process::exit(1);
+        })
+    });
+    let mpe_zones = args.iter().find_map(|arg| arg.strip_prefix("--mpe=")).map_or((0, 0), |zones| {
+        let mut members = zones.split(',').map(|members| members.trim().parse::<u8>());
+        match (members.next(), members.next(), members.next()) {
+            (Some(Ok(lower)), None, None) => (lower, 0),
+            (Some(Ok(lower)), Some(Ok(upper)), None) => (lower, upper),
+            _ => {
+                eprintln!("Invalid MPE zones: {}", zones);
+                std::process::exit(1);
+            }
+        }
+    });
+    let options = format::LoadOptions {
+        interpolation: parse_option(&args, "--interpolation", &interpolations, Interpolation::Linear),
+        legato: args.iter().any(|arg| arg == "--legato"),
+        mono: args.iter().any(|arg| arg == "--mono"),
+        mpe_zones,
+        note_priority: parse_option(&args, "--priority", &priorities, NotePriority::Last),
+        note_protection: (
+            args.iter().any(|arg| arg == "--protect-highest"),
+            args.iter().any(|arg| arg == "--protect-lowest"),
+        ),
+        polyphony,
+        portamento_curve: parse_option(&args, "--portamento-curve", &portamento_curves, GlideCurve::ConstantTime),
+        portamento_mode: parse_option(&args, "--portamento-mode", &portamento_modes, PortamentoMode::Always),
+        resample: args.iter().any(|arg| arg == "--resample"),
+        steal_policy: parse_option(&args, "--steal", &steal_policies, StealPolicy::Oldest),
+        storage: parse_option(&args, "--storage", &storages, SampleStorage::Memory),
+        ..Default::default()
+    };
+
+    if multisample {
+        let sampler = format::MultisampleLoader::from_file_with_options(path, &options).unwrap_or_else(|error| {
+            eprintln!("Failed to load {}: {}", path, error);
+            std::process::exit(1);
+        });
+        let (processor, proxy) = engine::CpalProcessor::new(Box::new(sampler));
+        let _audio_engine = engine::CpalAudioEngine::new(processor);
+        let _midi_engine = engine::MidirMidiEngine::new(proxy);
+        std::thread::park();
+    } else {
+        let (sampler, report) = format::SfzLoader::from_file_with_report(path, &options).unwrap_or_else(|error| {
+            eprintln!("Failed to load {}: {}", path, error);
+            std::process::exit(1);
+        });
+        println!("{}", report);
+        let (processor, proxy) = engine::CpalProcessor::new(Box::new(sampler));
+        let _audio_engine = engine::CpalAudioEngine::new(processor);
+        let mut reload_proxy = proxy.clone();
+
+        // Program changes switch instruments of program map, if one is given.
+        let _midi_engine = match programs {
+            Some(programs) => {
+                let map = format::ProgramMap::from_file(programs).expect("Failed to read program map.");
+                let mut program_proxy = proxy.clone();
+                let changer = format::ProgramChanger::new(map, options.clone(), proxy, move |sampler, _| {
+                    if program_proxy.swap_processor(Box::new(sampler)).is_err() {
+                        eprintln!("Processor is not keeping up, program change was dropped.");
+                    }
+                });
+                engine::MidirMidiEngine::new(changer)
+            }
+            None => engine::MidirMidiEngine::new(proxy),
+        };
+
+        // Reloaded instruments are swapped into running processor.
+        let watcher = watch.then(|| {
+            format::SfzWatcher::new(path, options, &report, move |sampler, _| {
+                if reload_proxy.swap_processor(Box::new(sampler)).is_err() {
+                    eprintln!("Processor is not keeping up, reloaded instrument was dropped.");
+                }
+            })
+        });
+        let _watcher = watcher.transpose().unwrap_or_else(|error| {
+            eprintln!("Failed to watch {}: {}", path, error);
+            std::process::exit(1);
+        });
+        std::thread::park();
+    }
+}
 
-    std::thread::park();
+/// Returns value of command line option (e.g. `--priority=lowest`), exits if value is unknown.
+fn parse_option<T: Copy>(args: &[String], name: &str, values: &[(&str, T)], default: T) -> T {
+    let Some(value) = args.iter().find_map(|arg| arg.strip_prefix(name)?.strip_prefix('=')) else {
+        return default;
+    };
+    match values.iter().find(|(key, _)| *key == value) {
+        Some((_, value)) => *value,
+        None => {
+            eprintln!("Unknown value for {}: {}", name, value);
+            std::process::exit(1);
+        }
+    }
 }
@@ -2,6 +2,11 @@ mod sampler;
 mod sine;
 
 pub use sampler::{
-    AudioFileSound, AudioFileVoice, OscillatorSound, OscillatorVoice, Sampler, SamplerSound, SamplerVoice,
+    AudioFileSound, AudioFileVoice, ExpressionModulation, GlideCurve, Interpolation, LoopMode, NotePriority,
+    PortamentoMode, SameNoteBehavior, SampleData, SampleStreamer, Sampler, SamplerSound, SamplerVoice, StealPolicy,
+    DEFAULT_POLYPHONY, DEFAULT_RESERVED_VOICES,
 };
+#[allow(unused_imports)]
+pub use sampler::{OscillatorSound, OscillatorVoice, SampleStream};
+#[allow(unused_imports)]
 pub use sine::Sine;
@@ -1,20 +1,73 @@
+mod aiff_file;
 mod audio_file_sound;
 mod audio_file_voice;
+mod channel_state;
+mod expression;
+mod glide;
+mod interpolation;
 mod linear_adsr;
+mod midi_mode;
+mod mpe_zones;
+mod note_stack;
+mod offline_resampler;
 mod oscillator_sound;
 mod oscillator_voice;
+mod pitch_bend;
+mod sample_data;
+mod sample_streamer;
 mod sampler_sound;
 mod sampler_voice;
+mod steal_policy;
 
 use crate::base::{AudioProcessor, MidiMessage, MidiReceiver, Parameter, ParameterId, ParameterValue};
-pub use audio_file_sound::AudioFileSound;
+use aiff_file::AiffFile;
+pub use audio_file_sound::{AudioFileSound, LoopMode};
 pub use audio_file_voice::AudioFileVoice;
+use channel_state::ChannelState;
+use expression::Expression;
+pub use expression::ExpressionModulation;
+use glide::Glide;
+pub use glide::{GlideCurve, PortamentoMode};
+pub use interpolation::Interpolation;
 pub use linear_adsr::LinearAdsr;
+use midi_mode::MidiMode;
+use mpe_zones::MpeZones;
+pub use note_stack::NotePriority;
+use note_stack::NoteStack;
 pub use oscillator_sound::OscillatorSound;
 pub use oscillator_voice::OscillatorVoice;
-pub use sampler_sound::SamplerSound;
+use pitch_bend::PitchBend;
+use sample_data::MappedWav;
+pub use sample_data::SampleData;
+pub use sample_streamer::{SampleStream, SampleStreamer};
+pub use sampler_sound::{SameNoteBehavior, SamplerSound};
 pub use sampler_voice::SamplerVoice;
 use std::sync::Arc;
+pub use steal_policy::StealPolicy;
+
+/// Maximum number of MIDI events queued for next processed buffer, further events are handled immediately.
+const MAX_QUEUED_EVENTS: usize = 256;
+
+/// Default number of voices playing notes (polyphony), if instrument doesn't specify it.
+pub const DEFAULT_POLYPHONY: usize = 64;
+
+/// Default number of voices reserved for fade-out of stolen voices.
+pub const DEFAULT_RESERVED_VOICES: usize = 4;
+
+/// Longest portamento time in seconds set via portamento time control (CC 5).
+const MAX_PORTAMENTO_TIME: f32 = 4.0;
+
+/// Sustain pedal value from which released notes are shortened less (half-pedaling), below dampers are fully down.
+const HALF_PEDAL_MIN: u8 = 16;
+
+/// Release time scale at the top of the half-pedal range (just below full sustain).
+const HALF_PEDAL_MAX_RELEASE_SCALE: f32 = 20.0;
+
+/// Velocity scale while soft pedal is fully pressed.
+const SOFT_PEDAL_VELOCITY_SCALE: f32 = 0.7;
+
+/// Fade-out time of stolen voices in seconds, avoids clicks.
+const STEAL_FADE_TIME: f32 = 0.005;
 
 /// Sampler instrument processor.
 #[derive(Debug)]
@@ -26,17 +79,71 @@ where
     /// Number of output channels.
     channel_count: u16,
 
+    /// MIDI channel states.
+    channels: [ChannelState; 16],
+
+    /// MIDI events queued for next processed buffer, with frame offset (ordered by offset).
+    events: Vec<(usize, MidiMessage)>,
+
+    /// Whether overlapping notes in mono mode change pitch without restarting envelope, otherwise notes retrigger.
+    legato: bool,
+
     /// Internal audio buffer (to mix stereo to mono).
     internal_buffer: Box<[f32]>,
 
+    /// MIDI channel mode (omni on/off, mono/poly).
+    midi_mode: MidiMode,
+
+    /// MPE zone layout, MPE is disabled without zones.
+    mpe_zones: MpeZones,
+
     /// Next voice priority.
     next_voice_priority: u32,
 
+    /// Whether highest and lowest playing notes are protected from stealing (e.g. melody and bass).
+    note_protection: (bool, bool),
+
+    /// Note priority in mono mode.
+    note_priority: NotePriority,
+
+    /// Held notes of each channel in mono mode (notes of all channels on first stack with omni on).
+    note_stacks: [NoteStack; 16],
+
+    /// Portamento curve.
+    portamento_curve: GlideCurve,
+
+    /// Portamento mode.
+    portamento_mode: PortamentoMode,
+
+    /// Number of voices reserved for fade-out of stolen voices, polyphony is voice count minus reserved voices.
+    reserved_voices: usize,
+
+    /// Whether sounds are converted to engine sample rate on reset.
+    resample: bool,
+
+    /// Sample rate in Hz that sounds were converted to, if converted.
+    resampled_rate: Option<f32>,
+
     /// Sampler sounds.
     sounds: Vec<Arc<Sound>>,
 
-    /// Sustain pedal state.
-    sustain_pedal_pressed: bool,
+    /// Voice stealing policy.
+    steal_policy: StealPolicy,
+
+    /// MIDI channel of each voice.
+    voice_channels: Vec<u8>,
+
+    /// Whether each voice is held by sostenuto pedal.
+    voice_sostenuto: Vec<bool>,
+
+    /// Index of sound (region) each voice plays.
+    voice_sounds: Vec<usize>,
+
+    /// Whether each voice is fading out after being stolen (or cut).
+    voice_stolen: Vec<bool>,
+
+    /// MIDI velocity of note each voice plays.
+    voice_velocities: Vec<u8>,
 
     /// Sampler voices.
     voices: Vec<Voice>,
@@ -46,10 +153,28 @@ impl<S: SamplerSound, V: SamplerVoice<S>> Sampler<S, V> {
     pub fn new() -> Self {
         Sampler {
             channel_count: 0,
+            channels: [ChannelState::new(); 16],
+            events: Vec::with_capacity(MAX_QUEUED_EVENTS),
+            legato: false,
             internal_buffer: Box::new([]),
+            midi_mode: MidiMode::new(),
+            mpe_zones: MpeZones::new(),
             next_voice_priority: 0,
+            note_protection: (false, false),
+            note_priority: NotePriority::Last,
+            note_stacks: std::array::from_fn(|_| NoteStack::new()),
+            portamento_curve: GlideCurve::ConstantTime,
+            portamento_mode: PortamentoMode::Always,
+            reserved_voices: 0,
+            resample: false,
+            resampled_rate: None,
             sounds: Vec::new(),
-            sustain_pedal_pressed: false,
+            steal_policy: StealPolicy::Oldest,
+            voice_channels: Vec::new(),
+            voice_sostenuto: Vec::new(),
+            voice_sounds: Vec::new(),
+            voice_stolen: Vec::new(),
+            voice_velocities: Vec::new(),
             voices: Vec::new(),
         }
     }
@@ -62,11 +187,157 @@ impl<S: SamplerSound, V: SamplerVoice<S>> Sampler<S, V> {
     /// Adds a voice.
     pub fn add_voice(&mut self, voice: V) {
         self.voices.push(voice);
+        self.voice_channels.push(0);
+        self.voice_sostenuto.push(false);
+        self.voice_sounds.push(0);
+        self.voice_stolen.push(false);
+        self.voice_velocities.push(0);
+    }
+
+    /// Sets whether overlapping notes in mono mode change pitch without restarting envelope (legato) or retrigger.
+    pub fn set_legato(&mut self, legato: bool) {
+        self.legato = legato;
+    }
+
+    /// Sets mono mode (one note per channel, one note in total with omni on), also switched by MIDI mode messages.
+    pub fn set_mono(&mut self, mono: bool) {
+        self.midi_mode.set_mono(mono);
+        self.all_notes_off(true);
+    }
+
+    /// Sets MPE zones by their number of member channels, 0 disables a zone (MPE is off without zones).
+    ///
+    /// Zones can also be configured via MIDI (MPE configuration message), upper zone shrinks if zones overlap.
+    pub fn set_mpe_zones(&mut self, lower_members: u8, upper_members: u8) {
+        self.configure_mpe_zone(15, upper_members);
+        self.configure_mpe_zone(0, lower_members);
+    }
+
+    /// Sets whether highest and lowest playing notes are protected from stealing (e.g. melody and bass).
+    pub fn set_note_protection(&mut self, highest: bool, lowest: bool) {
+        self.note_protection = (highest, lowest);
+    }
+
+    /// Sets which of the held notes sounds in mono mode.
+    pub fn set_note_priority(&mut self, note_priority: NotePriority) {
+        self.note_priority = note_priority;
+    }
+
+    /// Sets portamento mode and curve.
+    pub fn set_portamento_mode(&mut self, mode: PortamentoMode, curve: GlideCurve) {
+        self.portamento_curve = curve;
+        self.portamento_mode = mode;
+    }
+
+    /// Sets number of voices reserved for fade-out of stolen voices, polyphony is voice count minus reserved voices.
+    ///
+    /// Without free reserved voices, stolen voices are cut (which may click).
+    pub fn set_reserved_voices(&mut self, reserved_voices: usize) {
+        self.reserved_voices = reserved_voices;
+    }
+
+    /// Sets voice stealing policy.
+    pub fn set_steal_policy(&mut self, steal_policy: StealPolicy) {
+        self.steal_policy = steal_policy;
+    }
+
+    /// Sets whether sounds are converted to engine sample rate on reset (slower reset, cheaper playback).
+    ///
+    /// Sounds played at root note then match their samples exactly.
+    pub fn set_resample(&mut self, resample: bool) {
+        self.resample = resample;
+    }
+
+    /// Returns index of voice for new note, steals voice if polyphony is exhausted.
+    ///
+    /// Stolen voices fade out while the note starts on a free (reserved) voice, otherwise a voice is cut.
+    fn allocate_voice(&mut self, midi_note: u8) -> usize {
+        let polyphony = self.voices.len().saturating_sub(self.reserved_voices).max(1);
+        let active = |&index: &usize| self.voices[index].is_playing() && !self.voice_stolen[index];
+        if (0..self.voices.len()).filter(active).count() >= polyphony {
+            if let Some(index) = self.steal_candidate(midi_note) {
+                self.voices[index].set_key_down(false);
+                self.voices[index].fade_out(STEAL_FADE_TIME);
+                self.voice_sostenuto[index] = false;
+                self.voice_stolen[index] = true;
+            }
+        }
+
+        // Use free voice, otherwise cut oldest fading voice or steal voice.
+        let free = self.voices.iter().position(|voice| !voice.is_playing());
+        let fading = (0..self.voices.len()).filter(|&index| self.voice_stolen[index]);
+        let oldest_fading = fading.min_by_key(|&index| self.voices[index].get_priority());
+        free.or(oldest_fading).or_else(|| self.steal_candidate(midi_note)).unwrap_or(0)
     }
 
     /// All notes off (usually triggered by a MIDI message).
     fn all_notes_off(&mut self, allow_tail: bool) {
-        self.voices.iter_mut().for_each(|voice| voice.stop_note(0.0, allow_tail));
+        self.note_stacks.iter_mut().for_each(NoteStack::clear);
+        for voice in self.voices.iter_mut() {
+            voice.set_key_down(false);
+            voice.stop_note(0.0, allow_tail);
+        }
+    }
+
+    /// All sound off (usually triggered by a MIDI message), voices playing on channel are silenced immediately.
+    fn all_sound_off(&mut self, midi_channel: u8) {
+        self.note_stacks[self.mono_group(midi_channel)].clear();
+        for index in 0..self.voices.len() {
+            if self.is_affected_by(self.voice_channels[index], midi_channel) {
+                self.voices[index].set_key_down(false);
+                self.voices[index].stop_note(0.0, false);
+                self.voice_sostenuto[index] = false;
+            }
+        }
+    }
+
+    /// Handles channel mode messages (CC 124 to 127, usually triggered by a MIDI message), also turns all notes off.
+    fn channel_mode(&mut self, control: u8, value: u8) {
+        if self.midi_mode.handle_mode_control(control, value) {
+            self.all_notes_off(true);
+        }
+    }
+
+    /// Handles channel pressure (usually triggered by a MIDI message), voices playing on channel are modulated.
+    ///
+    /// Pressure of MPE member channels is note pressure, pressure of master channel applies to whole zone.
+    fn channel_pressure(&mut self, midi_channel: u8, value: u8) {
+        self.channels[midi_channel as usize & 0x0F].pressure = value.min(127) as f32 / 127.0;
+        for index in 0..self.voices.len() {
+            if self.is_affected_by(self.voice_channels[index], midi_channel) {
+                let (channel_pressure, note_pressure) = self.voice_pressure(self.voice_channels[index]);
+                let voice = &mut self.voices[index];
+                voice.set_channel_pressure(channel_pressure);
+                if let Some(note_pressure) = note_pressure {
+                    voice.set_note_pressure(note_pressure);
+                }
+            }
+        }
+    }
+
+    /// Configures MPE zone of master channel (MPE configuration message), other channels are ignored.
+    ///
+    /// Bend range of zone is reset to 48 semitones on member channels and 2 semitones on master channel.
+    fn configure_mpe_zone(&mut self, master_channel: u8, member_count: u8) {
+        if !self.mpe_zones.configure(master_channel, member_count) {
+            return;
+        }
+        for (midi_channel, channel) in (0..).zip(self.channels.iter_mut()) {
+            if self.mpe_zones.master_channel(midi_channel) == Some(master_channel) {
+                let member = midi_channel != master_channel;
+                channel.pitch_bend_range = Some(if member { 48.0 } else { 2.0 });
+            }
+        }
+    }
+
+    /// Returns note that note on channel glides from (portamento) and remembers note as last note of channel.
+    ///
+    /// Portamento control (CC 84) sets source note of next note regardless of portamento state.
+    fn glide_source(&mut self, midi_channel: u8, midi_note: u8, overlapping: bool) -> Option<u8> {
+        let channel = &mut self.channels[midi_channel as usize & 0x0F];
+        let last_note = channel.last_note.replace(midi_note);
+        let glides = channel.portamento && (overlapping || self.portamento_mode == PortamentoMode::Always);
+        channel.portamento_control.take().or(if glides { last_note } else { None })
     }
 
     /// Returns sounds.
@@ -75,68 +346,193 @@ impl<S: SamplerSound, V: SamplerVoice<S>> Sampler<S, V> {
         &self.sounds
     }
 
-    /// Handles sustain pedal (usually triggered by a MIDI message).
-    fn sustain_pedal(&mut self, pressed: bool) {
-        self.sustain_pedal_pressed = pressed;
-        if !pressed {
-            self.voices
-                .iter_mut()
-                .filter(|voice| voice.is_playing() && !voice.is_key_down())
-                .for_each(|voice| voice.stop_note(0.0, true));
-        }
+    /// Returns voices, including voices reserved for fade-out of stolen voices.
+    #[allow(dead_code)]
+    pub fn get_voices(&self) -> &Vec<V> {
+        &self.voices
+    }
+
+    /// Returns whether voice playing on channel is affected by messages on MIDI channel (same channel or its MPE master).
+    fn is_affected_by(&self, voice_channel: u8, midi_channel: u8) -> bool {
+        voice_channel == midi_channel || self.mpe_zones.master_channel(voice_channel) == Some(midi_channel)
     }
 
-    /// Note off (usually triggered by a MIDI message).
-    fn note_off(&mut self, _midi_channel: u8, midi_note: u8, velocity: u8) {
-        self.voices.iter_mut().filter(|voice| voice.get_active_note() == Some(midi_note)).for_each(|voice| {
+    /// Masks voices of sound playing note on channel (not yet released) before note starts again, according to sound
+    /// behavior.
+    ///
+    /// Oldest voices are released (or cut) until fewer voices than allowed play the note, voices of other sounds (e.g.
+    /// layered regions) are not affected.
+    fn mask_same_note(&mut self, sound_index: usize, midi_channel: u8, midi_note: u8, midi_velocity: u8) {
+        let behavior = self.sounds[sound_index].same_note_behavior();
+        let Some(max_voices) = behavior.max_voices else {
+            return; // Notes overlap without limit.
+        };
+        let playing = |sampler: &Self, index: usize| {
+            let voice = &sampler.voices[index];
+            sampler.voice_sounds[index] == sound_index
+                && sampler.voice_channels[index] == midi_channel
+                && voice.get_active_note() == Some(midi_note)
+                && !voice.is_releasing()
+                && !sampler.voice_stolen[index]
+        };
+        let mut playing_count = (0..self.voices.len()).filter(|&index| playing(self, index)).count();
+        while playing_count >= max_voices.max(1) as usize {
+            // With self mask, louder voices are not masked by softer notes.
+            let maskable = (0..self.voices.len()).filter(|&index| {
+                playing(self, index) && (!behavior.self_mask || self.voice_velocities[index] <= midi_velocity)
+            });
+            let Some(index) = maskable.min_by_key(|&index| self.voices[index].get_priority()) else {
+                break;
+            };
+            let voice = &mut self.voices[index];
             voice.set_key_down(false);
-            if !self.sustain_pedal_pressed {
-                voice.stop_note(velocity as f32 / 127.0, true);
+            if behavior.cut {
+                voice.fade_out(STEAL_FADE_TIME);
+                self.voice_stolen[index] = true;
+            } else {
+                voice.stop_note(0.0, true);
             }
-        });
+            self.voice_sostenuto[index] = false;
+            playing_count -= 1;
+        }
     }
 
-    /// Note on (usually triggered by a MIDI message).
-    fn note_on(&mut self, _midi_channel: u8, midi_note: u8, midi_velocity: u8) {
-        if self.sounds.len() == 0 || self.voices.len() == 0 {
-            return;
+    /// Returns index of note stack used by channel in mono mode.
+    fn mono_group(&self, midi_channel: u8) -> usize {
+        if self.midi_mode.is_omni() {
+            0
+        } else {
+            midi_channel as usize & 0x0F
         }
+    }
 
-        // If hitting a note that's still ringing, stop it first (sustain pedal).
-        self.voices
-            .iter_mut()
-            .filter(|voice| voice.get_active_note().map_or(false, |note| note == midi_note))
-            .for_each(|voice| voice.stop_note(0.0, true));
+    /// Reset all controllers (usually triggered by a MIDI message): pedals, portamento, pitch bend, pressure and
+    /// parameter selection.
+    ///
+    /// Only the addressed channel is reset, portamento time, pitch bend range and timbre are kept.
+    fn reset_all_controllers(&mut self, midi_channel: u8) {
+        self.sustain_pedal(midi_channel, 0);
+        self.sostenuto_pedal(midi_channel, false);
+        self.soft_pedal(midi_channel, 0);
+        self.channels[midi_channel as usize & 0x0F].reset_controllers();
+        for index in 0..self.voices.len() {
+            let voice_channel = self.voice_channels[index];
+            if self.is_affected_by(voice_channel, midi_channel) {
+                let (bend, bend_range) = self.voice_pitch_bend(voice_channel);
+                let (channel_pressure, note_pressure) = self.voice_pressure(voice_channel);
+                let voice = &mut self.voices[index];
+                voice.set_pitch_bend(bend, bend_range);
+                voice.set_channel_pressure(channel_pressure);
+                if voice_channel == midi_channel {
+                    voice.set_note_pressure(note_pressure.unwrap_or(0.0)); // Also resets polyphonic key pressure.
+                }
+            }
+        }
+    }
 
-        // Filter matching sounds.
-        for sound in self.sounds.iter().filter(|sound| sound.applies_to_note(midi_note, midi_velocity)) {
-            // Find free voice or steal voice based on priority.
-            let voice = {
-                if let Some(voice) = self.voices.iter_mut().find(|voice| !voice.is_playing()) {
-                    voice
-                } else {
-                    self.voices.iter_mut().min_by_key(|voice| voice.get_priority()).unwrap()
+    /// Handles registered parameter controls, returns false for other controls.
+    ///
+    /// Handles MPE configuration message (RPN 6), bend range set on a member channel applies to all members of zone.
+    fn parameter_control(&mut self, midi_channel: u8, control: u8, value: u8) -> bool {
+        let channel = &mut self.channels[midi_channel as usize & 0x0F];
+        if control == 0x06 && channel.registered_parameter() == (0, 6) {
+            self.configure_mpe_zone(midi_channel, value);
+            return true;
+        }
+        if !channel.handle_parameter_control(control, value) {
+            return false;
+        }
+        if matches!(control, 0x06 | 0x26) && self.mpe_zones.is_member(midi_channel) {
+            let (bend_range, master_channel) = (channel.pitch_bend_range, self.mpe_zones.master_channel(midi_channel));
+            for (member_channel, channel) in (0..).zip(self.channels.iter_mut()) {
+                if self.mpe_zones.is_member(member_channel)
+                    && self.mpe_zones.master_channel(member_channel) == master_channel
+                {
+                    channel.pitch_bend_range = bend_range;
                 }
-            };
+            }
+        }
+        true
+    }
 
-            // Start note on voice.
-            voice.start_note(midi_note, midi_velocity as f32 / 127.0, sound.clone(), self.next_voice_priority);
-            voice.set_key_down(true);
-            // Newer note will be more important, ignore overflow for now.
-            self.next_voice_priority = self.next_voice_priority.wrapping_add(1);
+    /// Handles pitch bend (usually triggered by a MIDI message), voices playing on channel are bent.
+    ///
+    /// Bend of MPE master channel applies to whole zone, adding to bend of member channels.
+    fn pitch_bend(&mut self, midi_channel: u8, value: u16) {
+        self.channels[midi_channel as usize & 0x0F].set_pitch_bend(value);
+        for index in 0..self.voices.len() {
+            if self.is_affected_by(self.voice_channels[index], midi_channel) {
+                let (bend, bend_range) = self.voice_pitch_bend(self.voice_channels[index]);
+                self.voices[index].set_pitch_bend(bend, bend_range);
+            }
         }
     }
-}
-impl<S: SamplerSound, V: SamplerVoice<S>> AudioProcessor for Sampler<S, V> {
-    fn get_parameter(&self, _id: ParameterId) -> Option<ParameterValue> {
-        None
+
+    /// Handles polyphonic key pressure (usually triggered by a MIDI message), voices playing note on channel are modulated.
+    fn poly_aftertouch(&mut self, midi_channel: u8, midi_note: u8, value: u8) {
+        for (voice, voice_channel) in self.voices.iter_mut().zip(&self.voice_channels) {
+            if *voice_channel == midi_channel && voice.get_active_note() == Some(midi_note) {
+                voice.set_note_pressure(value.min(127) as f32 / 127.0);
+            }
+        }
     }
 
-    fn list_parameters(&self) -> &[Parameter] {
-        &[]
+    /// Handles timbre (CC 74, usually triggered by a MIDI message), timbre of MPE master channel applies to whole zone.
+    fn timbre(&mut self, midi_channel: u8, value: u8) {
+        let timbre = value.min(127) as f32 / 127.0;
+        self.channels[midi_channel as usize & 0x0F].timbre = timbre;
+        for index in 0..self.voices.len() {
+            if self.is_affected_by(self.voice_channels[index], midi_channel) {
+                self.voices[index].set_timbre(timbre);
+            }
+        }
     }
 
-    fn process(&mut self, out_buffer: &mut [f32]) {
+    /// Returns soft and sustain pedal values for voice playing on channel, pedals of MPE master channel apply to whole
+    /// zone.
+    fn voice_pedals(&self, voice_channel: u8) -> (u8, u8) {
+        let channel = &self.channels[voice_channel as usize & 0x0F];
+        match self.mpe_zones.master_channel(voice_channel) {
+            Some(master_channel) if master_channel != voice_channel => {
+                let master = &self.channels[master_channel as usize];
+                (channel.soft_pedal.max(master.soft_pedal), channel.sustain_pedal.max(master.sustain_pedal))
+            }
+            _ => (channel.soft_pedal, channel.sustain_pedal),
+        }
+    }
+
+    /// Returns pitch bend (-1.0 to 1.0) and bend range in semitones for voice playing on channel.
+    ///
+    /// On MPE member channels, bend of master channel adds to bend of member channel (and so do their ranges).
+    fn voice_pitch_bend(&self, voice_channel: u8) -> (f32, Option<f32>) {
+        let channel = &self.channels[voice_channel as usize & 0x0F];
+        match self.mpe_zones.master_channel(voice_channel) {
+            Some(master_channel) if master_channel != voice_channel => {
+                let master = &self.channels[master_channel as usize];
+                let ranges = (channel.pitch_bend_range.unwrap_or(48.0), master.pitch_bend_range.unwrap_or(2.0));
+                let semitones = channel.pitch_bend * ranges.0 + master.pitch_bend * ranges.1;
+                let bend_range = ranges.0 + ranges.1;
+                (if bend_range > 0.0 { semitones / bend_range } else { 0.0 }, Some(bend_range))
+            }
+            _ => (channel.pitch_bend, channel.pitch_bend_range),
+        }
+    }
+
+    /// Returns channel pressure and note pressure (if set by channel) for voice playing on channel.
+    ///
+    /// On MPE member channels, channel pressure is note pressure and pressure of master channel is channel pressure.
+    fn voice_pressure(&self, voice_channel: u8) -> (f32, Option<f32>) {
+        let channel = &self.channels[voice_channel as usize & 0x0F];
+        match self.mpe_zones.master_channel(voice_channel) {
+            Some(master_channel) if master_channel != voice_channel => {
+                (self.channels[master_channel as usize].pressure, Some(channel.pressure))
+            }
+            _ => (channel.pressure, None),
+        }
+    }
+
+    /// Renders voices into output buffer.
+    fn render(&mut self, out_buffer: &mut [f32]) {
         // Prepare internal buffer.
         let frame_count = out_buffer.len() / self.channel_count as usize;
         let internal_buffer = &mut self.internal_buffer[0..2 * frame_count]; // Stereo.
@@ -157,33 +553,325 @@ impl<S: SamplerSound, V: SamplerVoice<S>> AudioProcessor for Sampler<S, V> {
         }
     }
 
+    /// Handles soft pedal (usually triggered by a MIDI message), softens notes played on channel while pressed.
+    fn soft_pedal(&mut self, midi_channel: u8, value: u8) {
+        self.channels[midi_channel as usize & 0x0F].soft_pedal = value;
+    }
+
+    /// Handles sostenuto pedal (usually triggered by a MIDI message), only holds notes on channel that are down when
+    /// pressed.
+    fn sostenuto_pedal(&mut self, midi_channel: u8, pressed: bool) {
+        let was_pressed = std::mem::replace(&mut self.channels[midi_channel as usize & 0x0F].sostenuto_pedal, pressed);
+        for index in 0..self.voices.len() {
+            let voice_channel = self.voice_channels[index];
+            if !self.is_affected_by(voice_channel, midi_channel) {
+                continue;
+            }
+            if pressed && !was_pressed {
+                let voice = &self.voices[index];
+                self.voice_sostenuto[index] = voice.is_playing() && voice.is_key_down();
+            } else if !pressed {
+                let release_scale = self.sustain_release_scale(voice_channel);
+                let voice = &mut self.voices[index];
+                if let (true, Some(release_scale)) =
+                    (self.voice_sostenuto[index] && !voice.is_key_down(), release_scale)
+                {
+                    voice.set_release_scale(release_scale);
+                    voice.stop_note(0.0, true);
+                }
+                self.voice_sostenuto[index] = false;
+            }
+        }
+    }
+
+    /// Returns voice to steal for new note according to steal policy, highest and lowest notes may be protected.
+    fn steal_candidate(&self, midi_note: u8) -> Option<usize> {
+        let candidates =
+            || (0..self.voices.len()).filter(|&index| self.voices[index].is_playing() && !self.voice_stolen[index]);
+        let notes = || candidates().filter_map(|index| self.voices[index].get_active_note());
+        let protected =
+            (notes().max().filter(|_| self.note_protection.0), notes().min().filter(|_| self.note_protection.1));
+        let unprotected = |&index: &usize| {
+            let note = self.voices[index].get_active_note();
+            note != protected.0 && note != protected.1
+        };
+        let order = |a: &usize, b: &usize| {
+            let (a, b) = (&self.voices[*a], &self.voices[*b]);
+            let oldest = a.get_priority().cmp(&b.get_priority());
+            match self.steal_policy {
+                StealPolicy::Oldest => oldest,
+                StealPolicy::Quietest => a.get_level().total_cmp(&b.get_level()).then(oldest),
+                StealPolicy::ReleasingFirst => b.is_releasing().cmp(&a.is_releasing()).then(oldest),
+                StealPolicy::SameNoteFirst => {
+                    let same_note = |voice: &V| voice.get_active_note() == Some(midi_note);
+                    same_note(b).cmp(&same_note(a)).then(oldest)
+                }
+            }
+        };
+
+        // Protected notes are stolen if all voices play them.
+        candidates().filter(unprotected).min_by(order).or_else(|| candidates().min_by(order))
+    }
+
+    /// Handles sustain pedal (usually triggered by a MIDI message), holds notes on channel, partial values shorten
+    /// release times.
+    fn sustain_pedal(&mut self, midi_channel: u8, value: u8) {
+        self.channels[midi_channel as usize & 0x0F].sustain_pedal = value;
+        for index in 0..self.voices.len() {
+            let voice_channel = self.voice_channels[index];
+            if !self.is_affected_by(voice_channel, midi_channel) {
+                continue;
+            }
+            if let Some(release_scale) = self.sustain_release_scale(voice_channel) {
+                let voice = &mut self.voices[index];
+                if voice.is_playing() && !voice.is_key_down() && !self.voice_sostenuto[index] {
+                    voice.set_release_scale(release_scale);
+                    voice.stop_note(0.0, true);
+                }
+            }
+        }
+    }
+
+    /// Returns release time scale of released notes playing on channel for sustain pedal value, none if notes are held.
+    fn sustain_release_scale(&self, voice_channel: u8) -> Option<f32> {
+        match self.voice_pedals(voice_channel).1 {
+            64.. => None,
+            value if value >= HALF_PEDAL_MIN => {
+                let depth = (value - HALF_PEDAL_MIN) as f32 / (64 - HALF_PEDAL_MIN) as f32;
+                Some(1.0 + depth * (HALF_PEDAL_MAX_RELEASE_SCALE - 1.0))
+            }
+            _ => Some(1.0),
+        }
+    }
+
+    /// Note off (usually triggered by a MIDI message), only stops note on same channel (e.g. MPE notes of equal pitch).
+    ///
+    /// In mono mode, releasing the sounding note returns to the held note with highest priority.
+    fn note_off(&mut self, midi_channel: u8, midi_note: u8, velocity: u8) {
+        if self.midi_mode.is_mono() {
+            let stack = &mut self.note_stacks[self.mono_group(midi_channel)];
+            let sounding = stack.sounding_note(self.note_priority);
+            if stack.remove(midi_channel, midi_note)
+                && sounding.is_some_and(|note| (note.0, note.1) == (midi_channel, midi_note))
+            {
+                if let Some((channel, note, velocity)) = stack.sounding_note(self.note_priority) {
+                    self.switch_mono_note(channel, note, velocity, true);
+                    return;
+                }
+            }
+        }
+
+        let release_scale = self.sustain_release_scale(midi_channel);
+        let voices = self.voices.iter_mut().zip(&self.voice_channels).zip(&self.voice_sostenuto);
+        for ((voice, voice_channel), sostenuto) in voices {
+            if *voice_channel == midi_channel && voice.get_active_note() == Some(midi_note) {
+                voice.set_key_down(false);
+                if let (false, Some(release_scale)) = (*sostenuto, release_scale) {
+                    voice.set_release_scale(release_scale);
+                    voice.stop_note(velocity as f32 / 127.0, true);
+                }
+            }
+        }
+    }
+
+    /// Note on (usually triggered by a MIDI message).
+    ///
+    /// In mono mode, notes are held on a note stack and only the held note with highest priority sounds.
+    fn note_on(&mut self, midi_channel: u8, midi_note: u8, midi_velocity: u8) {
+        if self.sounds.is_empty() || self.voices.is_empty() {
+            return;
+        }
+
+        // Soft pedal lowers velocity, which may also select softer sounds.
+        let soft_scale = 1.0 - (1.0 - SOFT_PEDAL_VELOCITY_SCALE) * self.voice_pedals(midi_channel).0 as f32 / 127.0;
+        let midi_velocity = ((midi_velocity as f32 * soft_scale).round() as u8).max(1);
+
+        if self.midi_mode.is_mono() {
+            let stack = &mut self.note_stacks[self.mono_group(midi_channel)];
+            let overlapping = stack.sounding_note(self.note_priority).is_some();
+            stack.push(midi_channel, midi_note, midi_velocity);
+            if stack.sounding_note(self.note_priority) == Some((midi_channel, midi_note, midi_velocity)) {
+                self.switch_mono_note(midi_channel, midi_note, midi_velocity, overlapping);
+            }
+        } else {
+            let mut voices = self.voices.iter().zip(&self.voice_channels);
+            let overlapping = voices.any(|(voice, channel)| *channel == midi_channel && voice.is_key_down());
+            let glide_source = self.glide_source(midi_channel, midi_note, overlapping);
+            self.start_voices(midi_channel, midi_note, midi_velocity, glide_source);
+        }
+    }
+
+    /// Starts voices playing note with all matching sounds, gliding from source note if set.
+    fn start_voices(&mut self, midi_channel: u8, midi_note: u8, midi_velocity: u8, glide_source: Option<u8>) {
+        // If hitting a note that's still ringing on channel, matching sounds decide whether it keeps ringing.
+        for sound_index in 0..self.sounds.len() {
+            if self.sounds[sound_index].applies_to_note(midi_note, midi_velocity) {
+                self.mask_same_note(sound_index, midi_channel, midi_note, midi_velocity);
+            }
+        }
+
+        // Filter matching sounds.
+        for sound_index in 0..self.sounds.len() {
+            if !self.sounds[sound_index].applies_to_note(midi_note, midi_velocity) {
+                continue;
+            }
+            let sound = self.sounds[sound_index].clone();
+            let index = self.allocate_voice(midi_note);
+            self.voice_channels[index] = midi_channel;
+            self.voice_sostenuto[index] = false;
+            self.voice_sounds[index] = sound_index;
+            self.voice_stolen[index] = false;
+            self.voice_velocities[index] = midi_velocity;
+
+            // Start note on voice, current pitch bend, pressure and timbre of channel apply.
+            let (bend, bend_range) = self.voice_pitch_bend(midi_channel);
+            let (channel_pressure, note_pressure) = self.voice_pressure(midi_channel);
+            let channel = &self.channels[midi_channel as usize & 0x0F];
+            let (portamento_time, timbre) = (channel.portamento_time, channel.timbre);
+            let voice = &mut self.voices[index];
+            voice.set_pitch_bend(bend, bend_range);
+            voice.set_channel_pressure(channel_pressure);
+            voice.set_timbre(timbre);
+            voice.start_note(midi_note, midi_velocity as f32 / 127.0, sound, self.next_voice_priority);
+            if let Some(source_note) = glide_source {
+                let semitones = source_note as f32 - midi_note as f32;
+                voice.start_glide(semitones, portamento_time, self.portamento_curve);
+            }
+            if let Some(note_pressure) = note_pressure {
+                voice.set_note_pressure(note_pressure);
+            }
+            voice.set_key_down(true);
+            // Newer note will be more important, ignore overflow for now.
+            self.next_voice_priority = self.next_voice_priority.wrapping_add(1);
+        }
+    }
+
+    /// Switches sounding note of mono group (channel, or all channels with omni on), overlapping if previous note is held.
+    ///
+    /// With legato, voices of the sounding note change pitch, otherwise voices of group are released and note restarts.
+    fn switch_mono_note(&mut self, midi_channel: u8, midi_note: u8, midi_velocity: u8, overlapping: bool) {
+        let glide_source = self.glide_source(midi_channel, midi_note, overlapping);
+        let legato = self.legato && overlapping;
+        let portamento_time = self.channels[midi_channel as usize & 0x0F].portamento_time;
+        let omni = self.midi_mode.is_omni();
+        let mut changed = false;
+        for index in 0..self.voices.len() {
+            let voice = &mut self.voices[index];
+            if !voice.is_playing() || !(omni || self.voice_channels[index] == midi_channel) {
+                continue;
+            }
+            if legato && voice.is_key_down() {
+                voice.change_note(midi_note);
+                if let Some(source_note) = glide_source {
+                    let semitones = source_note as f32 - midi_note as f32;
+                    voice.start_glide(semitones, portamento_time, self.portamento_curve);
+                }
+                self.voice_channels[index] = midi_channel;
+                changed = true;
+            } else {
+                voice.set_key_down(false);
+                voice.stop_note(0.0, true);
+                self.voice_sostenuto[index] = false;
+            }
+        }
+        if !changed {
+            self.start_voices(midi_channel, midi_note, midi_velocity, glide_source);
+        }
+    }
+}
+impl<S: SamplerSound, V: SamplerVoice<S>> AudioProcessor for Sampler<S, V> {
+    fn get_parameter(&self, _id: ParameterId) -> Option<ParameterValue> {
+        None
+    }
+
+    fn list_parameters(&self) -> &[Parameter] {
+        &[]
+    }
+
+    fn process(&mut self, out_buffer: &mut [f32]) {
+        // Render until next event, so that events take effect at their frame offset.
+        let channel_count = self.channel_count as usize;
+        let frame_count = out_buffer.len() / channel_count;
+        let mut position = 0;
+        let mut events = std::mem::take(&mut self.events);
+        for (frame_offset, message) in events.drain(..) {
+            let frame_offset = frame_offset.min(frame_count);
+            if frame_offset > position {
+                self.render(&mut out_buffer[position * channel_count..frame_offset * channel_count]);
+                position = frame_offset;
+            }
+            self.handle_midi_message(message);
+        }
+        self.events = events; // Keep allocation.
+        self.render(&mut out_buffer[position * channel_count..]);
+    }
+
     fn reset(&mut self, sample_rate: f32, max_buffer_size: usize) {
         // Allocate internal resources.
         self.internal_buffer = vec![0.0; 2 * max_buffer_size].into_boxed_slice();
 
         // Reset voices.
         self.voices.iter_mut().for_each(|voice| voice.reset(sample_rate, max_buffer_size));
+
+        // Convert sounds if sample rate changed, voices don't hold sounds after reset.
+        if self.resample && self.resampled_rate != Some(sample_rate) {
+            S::resample(&mut self.sounds, sample_rate);
+            self.resampled_rate = Some(sample_rate);
+        }
     }
 
     fn set_channel_layout(&mut self, _input_channels: u16, output_channels: u16) {
         self.channel_count = output_channels;
     }
 
-    fn set_parameter(&mut self, _id: ParameterId, _value: ParameterValue) {
-        return;
-    }
+    fn set_parameter(&mut self, _id: ParameterId, _value: ParameterValue) {}
 }
 impl<S: SamplerSound, V: SamplerVoice<S>> MidiReceiver for Sampler<S, V> {
     fn handle_midi_message(&mut self, message: MidiMessage) {
+        if message.channel().is_some_and(|channel| !self.midi_mode.receives(channel)) {
+            return;
+        }
         match message {
-            MidiMessage::ControlChange(_, 0x40, value) => self.sustain_pedal(value >= 64),
+            MidiMessage::ChannelPressure(channel, value) => self.channel_pressure(channel, value),
+            MidiMessage::ControlChange(channel, control, value) if self.parameter_control(channel, control, value) => {}
+            MidiMessage::ControlChange(channel, 0x05, value) => {
+                self.channels[channel as usize & 0x0F].portamento_time =
+                    MAX_PORTAMENTO_TIME * (value.min(127) as f32 / 127.0).powi(2)
+            }
+            MidiMessage::ControlChange(channel, 0x40, value) => self.sustain_pedal(channel, value),
+            MidiMessage::ControlChange(channel, 0x41, value) => {
+                self.channels[channel as usize & 0x0F].portamento = value >= 64
+            }
+            MidiMessage::ControlChange(channel, 0x42, value) => self.sostenuto_pedal(channel, value >= 64),
+            MidiMessage::ControlChange(channel, 0x43, value) => self.soft_pedal(channel, value),
+            MidiMessage::ControlChange(channel, 0x4A, value) => self.timbre(channel, value),
+            MidiMessage::ControlChange(channel, 0x54, value) => {
+                self.channels[channel as usize & 0x0F].portamento_control = Some(value.min(127))
+            }
+            MidiMessage::ControlChange(channel, 0x78, _) => self.all_sound_off(channel),
+            MidiMessage::ControlChange(channel, 0x79, _) => self.reset_all_controllers(channel),
             MidiMessage::ControlChange(_, 0x7B, _) => self.all_notes_off(true),
+            MidiMessage::ControlChange(MidiMode::BASIC_CHANNEL, control @ 0x7C..=0x7F, value) => {
+                self.channel_mode(control, value)
+            }
             MidiMessage::NoteOff(channel, note, velocity) => self.note_off(channel, note, velocity),
             MidiMessage::NoteOn(channel, note, 0) => self.note_off(channel, note, 0), // MIDI running status.
             MidiMessage::NoteOn(channel, note, velocity) => self.note_on(channel, note, velocity),
+            MidiMessage::PitchBend(channel, value) => self.pitch_bend(channel, value),
+            MidiMessage::PolyAftertouch(channel, note, value) => self.poly_aftertouch(channel, note, value),
             _ => (),
         }
     }
+
+    fn handle_midi_message_at(&mut self, message: MidiMessage, frame_offset: usize) {
+        // Queue is ordered by offset, messages with equal offset keep their order.
+        if self.events.len() < MAX_QUEUED_EVENTS {
+            let index = self.events.partition_point(|(event_offset, _)| *event_offset <= frame_offset);
+            self.events.insert(index, (frame_offset, message));
+        } else {
+            self.handle_midi_message(message);
+        }
+    }
 }
 
 /// Unit tests.
@@ -231,6 +919,128 @@ mod tests {
         });
     }
 
+    #[test]
+    fn aftertouch() {
+        let mut sampler = Sampler::<DummySound, DummyVoice>::new();
+        sampler.add_sound(DummySound::new());
+        sampler.add_voice(DummyVoice::new());
+        sampler.add_voice(DummyVoice::new());
+        sampler.add_voice(DummyVoice::new());
+
+        // Channel pressure applies to voices on channel, key pressure only to voice playing note.
+        sampler.note_on(0, 48, 127);
+        sampler.note_on(0, 62, 127);
+        sampler.handle_midi_message(MidiMessage::ChannelPressure(0, 127));
+        sampler.handle_midi_message(MidiMessage::ChannelPressure(1, 64));
+        sampler.handle_midi_message(MidiMessage::PolyAftertouch(0, 62, 127));
+        sampler.handle_midi_message(MidiMessage::PolyAftertouch(1, 48, 127));
+        assert_eq!(sampler.voices[0].pressure, (1.0, 0.0));
+        assert_eq!(sampler.voices[1].pressure, (1.0, 1.0));
+
+        // New notes start with current pressure of channel.
+        sampler.note_on(1, 56, 127);
+        assert_eq!(sampler.voices[2].pressure, (64.0 / 127.0, 0.0));
+    }
+
+    #[test]
+    fn mpe() {
+        let mut sampler = Sampler::<DummySound, DummyVoice>::new();
+        sampler.add_sound(DummySound::new());
+        sampler.add_voice(DummyVoice::new());
+        sampler.add_voice(DummyVoice::new());
+
+        // MPE configuration message sets lower zone with 3 member channels.
+        [(0x65, 0), (0x64, 6), (0x06, 3)]
+            .into_iter()
+            .for_each(|(control, value)| sampler.handle_midi_message(MidiMessage::ControlChange(0, control, value)));
+        assert_eq!(sampler.channels[3].pitch_bend_range, Some(48.0));
+
+        // Equal notes on member channels are expressed individually.
+        sampler.note_on(1, 60, 127);
+        sampler.note_on(2, 60, 127);
+        sampler.handle_midi_message(MidiMessage::PitchBend(1, 16383));
+        sampler.handle_midi_message(MidiMessage::ChannelPressure(2, 127));
+        sampler.handle_midi_message(MidiMessage::ControlChange(1, 0x4A, 127));
+        assert_eq!(sampler.voices[0].pitch_bend, (48.0 / 50.0, Some(50.0)));
+        assert_eq!(sampler.voices[1].pitch_bend, (0.0, Some(50.0)));
+        assert_eq!((sampler.voices[0].pressure, sampler.voices[0].timbre), ((0.0, 0.0), 1.0));
+        assert_eq!((sampler.voices[1].pressure, sampler.voices[1].timbre), ((0.0, 1.0), 0.0));
+
+        // Master channel applies to whole zone, bend adds to bend of member channels.
+        sampler.handle_midi_message(MidiMessage::PitchBend(0, 0));
+        sampler.handle_midi_message(MidiMessage::ChannelPressure(0, 127));
+        assert_eq!(sampler.voices[0].pitch_bend, (46.0 / 50.0, Some(50.0)));
+        assert_eq!(sampler.voices[1].pitch_bend, (-2.0 / 50.0, Some(50.0)));
+        assert_eq!(sampler.voices[0].pressure, (1.0, 0.0));
+        assert_eq!(sampler.voices[1].pressure, (1.0, 1.0));
+
+        // Bend range set on member channel applies to all members, note off only stops note on its channel.
+        [(0x65, 0), (0x64, 0), (0x06, 24)]
+            .into_iter()
+            .for_each(|(control, value)| sampler.handle_midi_message(MidiMessage::ControlChange(3, control, value)));
+        assert_eq!(sampler.channels[1].pitch_bend_range, Some(24.0));
+        assert_eq!(sampler.channels[0].pitch_bend_range, Some(2.0));
+        sampler.note_off(1, 60, 0);
+        assert_eq!(sampler.voices[0].get_active_note(), None);
+        assert_eq!(sampler.voices[1].get_active_note(), Some(60));
+
+        // Zones set directly (e.g. from load options), disabled lower zone leaves its channels outside of zones.
+        sampler.set_mpe_zones(0, 5);
+        assert_eq!(sampler.mpe_zones.master_channel(1), None);
+        assert_eq!(sampler.mpe_zones.master_channel(10), Some(15));
+        assert_eq!(sampler.channels[10].pitch_bend_range, Some(48.0));
+    }
+
+    #[test]
+    fn pitch_bend() {
+        let mut sampler = Sampler::<DummySound, DummyVoice>::new();
+        sampler.add_sound(DummySound::new());
+        sampler.add_voice(DummyVoice::new());
+        sampler.add_voice(DummyVoice::new());
+        sampler.add_voice(DummyVoice::new());
+
+        // Only voices on bent channel are bent.
+        sampler.note_on(0, 48, 127);
+        sampler.note_on(1, 62, 127);
+        sampler.handle_midi_message(MidiMessage::PitchBend(1, 16383));
+        assert_eq!(sampler.voices[0].pitch_bend, (0.0, None));
+        assert_eq!(sampler.voices[1].pitch_bend, (1.0, None));
+
+        // RPN 0 sets bend range, new notes start with current bend of channel.
+        [(0x65, 0), (0x64, 0), (0x06, 12)]
+            .into_iter()
+            .for_each(|(control, value)| sampler.handle_midi_message(MidiMessage::ControlChange(1, control, value)));
+        sampler.note_on(1, 56, 127);
+        assert_eq!(sampler.voices[2].pitch_bend, (1.0, Some(12.0)));
+    }
+
+    #[test]
+    fn sample_accurate_events() {
+        let mut buffer: Box<[f32]> = vec![0.0; 512].into_boxed_slice();
+        let mut sampler = Sampler::<DummySound, DummyVoice>::new();
+        sampler.add_sound(DummySound::new());
+        sampler.add_voice(DummyVoice::new());
+        sampler.set_channel_layout(0, 2);
+        sampler.reset(1000.0, buffer.len());
+
+        // Events take effect at their frame offset, regardless of order they were received in.
+        sampler.handle_midi_message_at(MidiMessage::NoteOff(0, 62, 0), 200);
+        sampler.handle_midi_message_at(MidiMessage::NoteOn(0, 62, 127), 100);
+        sampler.process(&mut buffer);
+        buffer.chunks(2).enumerate().for_each(|(frame, samples)| {
+            let expected = if (100..200).contains(&frame) { 0.1 } else { 0.0 };
+            assert_eq!(samples[1], expected);
+        });
+
+        // Events are only applied once, offsets beyond buffer apply at its end.
+        sampler.handle_midi_message_at(MidiMessage::NoteOn(0, 62, 127), 1000);
+        buffer.fill(0.0);
+        sampler.process(&mut buffer);
+        assert!(buffer.iter().all(|sample| *sample == 0.0));
+        assert!(sampler.events.is_empty());
+        assert_eq!(sampler.voices[0].get_active_note(), Some(62));
+    }
+
     #[test]
     fn sustain_pedal() {
         let mut buffer: Box<[f32]> = vec![0.0; 512].into_boxed_slice();
@@ -244,7 +1054,7 @@ mod tests {
         sampler.note_on(0, 56, 127); // 56 on.
         sampler.note_off(0, 56, 0); // 56 off.
         sampler.note_on(0, 62, 127); // 62 on.
-        sampler.sustain_pedal(true);
+        sampler.sustain_pedal(0, 127);
         sampler.note_on(0, 48, 127); // 48 on.
         sampler.note_off(0, 62, 0); // 62 sustained.
         sampler.process(&mut buffer);
@@ -254,7 +1064,7 @@ mod tests {
             assert!((frame[1] - 0.2).abs() < 1e-16);
         });
 
-        sampler.sustain_pedal(false); // 62 off.
+        sampler.sustain_pedal(0, 0); // 62 off.
         buffer.fill(0.0);
         sampler.process(&mut buffer);
 
@@ -264,4 +1074,362 @@ mod tests {
             assert!((frame[1] - 0.1).abs() < 1e-16);
         });
     }
+
+    #[test]
+    fn pedals() {
+        let mut sampler = Sampler::<DummySound, DummyVoice>::new();
+        sampler.add_sound(DummySound::new());
+        (0..3).for_each(|_| sampler.add_voice(DummyVoice::new()));
+        sampler.set_channel_layout(0, 2);
+        sampler.reset(1000.0, 64);
+
+        // Sostenuto only holds notes that are down when pressed.
+        sampler.note_on(0, 60, 127);
+        sampler.sostenuto_pedal(0, true);
+        sampler.note_on(0, 64, 127);
+        sampler.note_off(0, 60, 0); // 60 held.
+        sampler.note_off(0, 64, 0); // 64 off.
+        assert_eq!(sampler.voices[0].get_active_note(), Some(60));
+        assert_eq!(sampler.voices[1].get_active_note(), None);
+        sampler.sostenuto_pedal(0, false); // 60 off.
+        assert_eq!(sampler.voices[0].get_active_note(), None);
+
+        // Half-pedaling releases notes with longer release time.
+        sampler.sustain_pedal(0, 40);
+        sampler.note_on(0, 60, 127);
+        sampler.note_off(0, 60, 0);
+        assert_eq!(sampler.voices[0].get_active_note(), None);
+        assert!((sampler.voices[0].release_scale - 10.5).abs() < 1e-6);
+        sampler.sustain_pedal(0, 0);
+
+        // Soft pedal lowers velocity.
+        sampler.soft_pedal(0, 127);
+        sampler.note_on(0, 60, 100);
+        assert!((sampler.voices[0].velocity - 70.0 / 127.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn channel_pedals() {
+        let mut sampler = Sampler::<DummySound, DummyVoice>::new();
+        sampler.add_sound(DummySound::new());
+        (0..3).for_each(|_| sampler.add_voice(DummyVoice::new()));
+        sampler.set_channel_layout(0, 2);
+        sampler.reset(1000.0, 64);
+
+        // Pedals only affect notes on their channel.
+        sampler.note_on(0, 60, 127);
+        sampler.note_on(1, 64, 127);
+        sampler.handle_midi_message(MidiMessage::ControlChange(1, 0x40, 127));
+        sampler.handle_midi_message(MidiMessage::ControlChange(1, 0x43, 127));
+        sampler.note_off(0, 60, 0); // 60 off.
+        sampler.note_off(1, 64, 0); // 64 sustained.
+        assert_eq!(sampler.voices[0].get_active_note(), None);
+        assert_eq!(sampler.voices[1].get_active_note(), Some(64));
+        sampler.note_on(0, 60, 100);
+        assert_eq!(sampler.voices[0].velocity, 100.0 / 127.0);
+        sampler.handle_midi_message(MidiMessage::ControlChange(0, 0x79, 0)); // Channel 1 keeps its pedals.
+        assert_eq!(sampler.voices[1].get_active_note(), Some(64));
+        sampler.handle_midi_message(MidiMessage::ControlChange(1, 0x79, 0)); // 64 off.
+        assert_eq!(sampler.voices[1].get_active_note(), None);
+        sampler.note_off(0, 60, 0);
+
+        // Pedals of MPE master channel apply to its zone only.
+        sampler.set_mpe_zones(3, 3);
+        sampler.note_on(1, 60, 127);
+        sampler.note_on(14, 64, 127);
+        sampler.handle_midi_message(MidiMessage::ControlChange(0, 0x40, 127));
+        sampler.handle_midi_message(MidiMessage::ControlChange(0, 0x43, 127));
+        sampler.note_off(1, 60, 0); // 60 sustained.
+        sampler.note_off(14, 64, 0); // 64 off.
+        assert_eq!(sampler.voices[0].get_active_note(), Some(60));
+        assert_eq!(sampler.voices[1].get_active_note(), None);
+        sampler.note_on(2, 62, 100);
+        assert!((sampler.voices[1].velocity - 70.0 / 127.0).abs() < 1e-6);
+        sampler.handle_midi_message(MidiMessage::ControlChange(0, 0x40, 0)); // 60 off.
+        assert_eq!(sampler.voices[0].get_active_note(), None);
+    }
+
+    #[test]
+    fn all_sound_off() {
+        let mut buffer: Box<[f32]> = vec![0.0; 512].into_boxed_slice();
+        let mut sampler = Sampler::<DummySound, DummyVoice>::new();
+        sampler.add_sound(DummySound::new());
+        sampler.add_voice(DummyVoice::new());
+        sampler.add_voice(DummyVoice::new());
+        sampler.set_channel_layout(0, 2);
+        sampler.reset(1000.0, buffer.len());
+
+        sampler.note_on(0, 62, 127); // 62 on.
+        sampler.note_on(1, 48, 127); // 48 on.
+        sampler.sustain_pedal(0, 127);
+        sampler.handle_midi_message(MidiMessage::ControlChange(0, 0x78, 0)); // 62 off (ignores pedal).
+        sampler.process(&mut buffer);
+
+        // 48 is still on (other channel).
+        buffer.chunks(2).for_each(|frame| {
+            assert!((frame[0] - 48.0 / 127.0).abs() < 1e-16);
+            assert!((frame[1] - 0.1).abs() < 1e-16);
+        });
+    }
+
+    #[test]
+    fn reset_all_controllers() {
+        let mut sampler = Sampler::<DummySound, DummyVoice>::new();
+        sampler.add_sound(DummySound::new());
+        sampler.add_voice(DummyVoice::new());
+        sampler.add_voice(DummyVoice::new());
+        sampler.set_channel_layout(0, 2);
+        sampler.reset(1000.0, 64);
+
+        sampler.handle_midi_message(MidiMessage::PitchBend(0, 16383));
+        sampler.handle_midi_message(MidiMessage::ChannelPressure(0, 127));
+        sampler.handle_midi_message(MidiMessage::ControlChange(0, 0x4A, 127));
+        sampler.note_on(0, 62, 127); // 62 on.
+        sampler.handle_midi_message(MidiMessage::PolyAftertouch(0, 62, 127));
+        sampler.sustain_pedal(0, 127);
+        sampler.note_off(0, 62, 0); // 62 sustained.
+        sampler.handle_midi_message(MidiMessage::ControlChange(0, 0x41, 127)); // Portamento on.
+        sampler.handle_midi_message(MidiMessage::ControlChange(1, 0x41, 127)); // Portamento on (other channel).
+        sampler.handle_midi_message(MidiMessage::ControlChange(0, 0x54, 48)); // Next note glides from 48.
+        sampler.handle_midi_message(MidiMessage::ControlChange(0, 0x79, 0)); // 62 off (pedal up).
+
+        // Bend and pressure are reset, timbre is kept.
+        let voice = &sampler.voices[0];
+        assert_eq!(voice.get_active_note(), None);
+        assert_eq!(voice.pitch_bend.0, 0.0);
+        assert_eq!(voice.pressure, (0.0, 0.0));
+        assert_eq!(voice.timbre, 1.0);
+        assert_eq!(sampler.channels[0].sustain_pedal, 0);
+
+        // Portamento and pending portamento control are reset, next note doesn't glide. Other channels are kept.
+        assert!(!sampler.channels[0].portamento);
+        assert!(sampler.channels[1].portamento);
+        assert_eq!(sampler.channels[0].portamento_control, None);
+        sampler.note_on(0, 64, 127); // 64 on.
+        assert_eq!(sampler.voices[0].get_active_note(), Some(64));
+        assert!(sampler.voices[0].glide.is_none());
+    }
+
+    #[test]
+    fn channel_mode() {
+        let mut buffer: Box<[f32]> = vec![0.0; 512].into_boxed_slice();
+        let mut sampler = Sampler::<DummySound, DummyVoice>::new();
+        sampler.add_sound(DummySound::new());
+        sampler.add_voice(DummyVoice::new());
+        sampler.add_voice(DummyVoice::new());
+        sampler.set_channel_layout(0, 2);
+        sampler.reset(1000.0, buffer.len());
+
+        // Mode messages are only received on basic channel and turn notes off.
+        sampler.note_on(1, 56, 127); // 56 on.
+        sampler.handle_midi_message(MidiMessage::ControlChange(1, 0x7C, 0)); // Ignored.
+        sampler.handle_midi_message(MidiMessage::ControlChange(0, 0x7C, 0)); // 56 off, omni off.
+        sampler.handle_midi_message(MidiMessage::NoteOn(1, 48, 127)); // Ignored.
+        assert!(sampler.voices.iter().all(|voice| !voice.is_playing()));
+
+        // Mono mode replaces notes.
+        sampler.handle_midi_message(MidiMessage::ControlChange(0, 0x7E, 1));
+        sampler.handle_midi_message(MidiMessage::NoteOn(0, 48, 127)); // 48 on.
+        sampler.handle_midi_message(MidiMessage::NoteOn(0, 62, 127)); // 48 off, 62 on.
+        sampler.process(&mut buffer);
+
+        buffer.chunks(2).for_each(|frame| {
+            assert!((frame[0] - 62.0 / 127.0).abs() < 1e-16);
+            assert!((frame[1] - 0.1).abs() < 1e-16);
+        });
+
+        // Poly and omni on.
+        sampler.handle_midi_message(MidiMessage::ControlChange(0, 0x7F, 0)); // 62 off.
+        sampler.handle_midi_message(MidiMessage::ControlChange(0, 0x7D, 0));
+        sampler.handle_midi_message(MidiMessage::NoteOn(1, 48, 127)); // 48 on.
+        sampler.handle_midi_message(MidiMessage::NoteOn(0, 62, 127)); // 62 on.
+        assert!(sampler.voices.iter().all(|voice| voice.is_playing()));
+    }
+
+    #[test]
+    fn mono() {
+        let mut sampler = Sampler::<DummySound, DummyVoice>::new();
+        sampler.add_sound(DummySound::new());
+        sampler.add_voice(DummyVoice::new());
+        sampler.add_voice(DummyVoice::new());
+        sampler.set_channel_layout(0, 2);
+        sampler.reset(1000.0, 64);
+        sampler.set_mono(true);
+        let sounding = |sampler: &Sampler<DummySound, DummyVoice>| {
+            let notes = sampler.voices.iter().filter(|voice| voice.is_key_down()).map(|voice| voice.get_active_note());
+            notes.collect::<Vec<_>>()
+        };
+
+        // Retrigger restarts notes, releasing note returns to previous note.
+        sampler.note_on(0, 48, 127);
+        sampler.note_on(0, 60, 100);
+        assert_eq!(sounding(&sampler), [Some(60)]);
+        sampler.note_off(0, 60, 0);
+        assert_eq!(sounding(&sampler), [Some(48)]);
+        assert!(sampler.voices.iter().any(|voice| voice.velocity == 1.0 && voice.is_key_down()));
+        sampler.note_off(0, 48, 0);
+        assert!(sampler.voices.iter().all(|voice| !voice.is_playing()));
+
+        // Lowest note priority ignores higher notes.
+        sampler.set_note_priority(NotePriority::Lowest);
+        sampler.note_on(0, 48, 127);
+        sampler.note_on(0, 60, 127);
+        assert_eq!(sounding(&sampler), [Some(48)]);
+        sampler.note_off(0, 60, 0);
+        assert_eq!(sounding(&sampler), [Some(48)]);
+        sampler.note_off(0, 48, 0);
+
+        // Legato keeps voice (and velocity) of overlapping notes.
+        sampler.set_note_priority(NotePriority::Highest);
+        sampler.set_legato(true);
+        sampler.note_on(0, 48, 127);
+        sampler.note_on(0, 60, 64);
+        sampler.note_on(0, 55, 32);
+        let voice = sampler.voices.iter().find(|voice| voice.is_key_down()).unwrap();
+        assert_eq!((voice.get_active_note(), voice.velocity), (Some(60), 1.0));
+        sampler.note_off(0, 60, 0);
+        let voice = sampler.voices.iter().find(|voice| voice.is_key_down()).unwrap();
+        assert_eq!((voice.get_active_note(), voice.velocity), (Some(55), 1.0));
+        assert_eq!(sampler.voices.iter().filter(|voice| voice.is_playing()).count(), 1);
+    }
+
+    #[test]
+    fn portamento() {
+        let mut sampler = Sampler::<DummySound, DummyVoice>::new();
+        sampler.add_sound(DummySound::new());
+        sampler.add_voice(DummyVoice::new());
+        sampler.add_voice(DummyVoice::new());
+        sampler.set_channel_layout(0, 2);
+        sampler.reset(1000.0, 64);
+
+        // Portamento is off, portamento control glides next note from source note.
+        sampler.note_on(0, 48, 127);
+        assert_eq!(sampler.voices[0].glide, None);
+        sampler.note_off(0, 48, 0);
+        sampler.handle_midi_message(MidiMessage::ControlChange(0, 0x54, 36));
+        sampler.note_on(0, 48, 127);
+        assert_eq!(sampler.voices[0].glide, Some((-12.0, 0.1, GlideCurve::ConstantTime)));
+        sampler.note_off(0, 48, 0);
+
+        // Portamento of other channel doesn't glide.
+        sampler.handle_midi_message(MidiMessage::ControlChange(1, 0x41, 127));
+        sampler.note_on(0, 48, 127);
+        assert_eq!(sampler.voices[0].glide, None);
+        sampler.note_off(0, 48, 0);
+
+        // Always glides from last note.
+        sampler.handle_midi_message(MidiMessage::ControlChange(0, 0x41, 127));
+        sampler.handle_midi_message(MidiMessage::ControlChange(0, 0x05, 127));
+        sampler.note_on(0, 60, 127);
+        assert_eq!(sampler.voices[0].glide, Some((-12.0, 4.0, GlideCurve::ConstantTime)));
+        sampler.note_off(0, 60, 0);
+
+        // Legato-only glides overlapping notes.
+        sampler.set_portamento_mode(PortamentoMode::LegatoOnly, GlideCurve::ConstantRate);
+        sampler.note_on(0, 55, 127);
+        assert_eq!(sampler.voices[0].glide, None);
+        sampler.note_on(0, 50, 127);
+        assert_eq!(sampler.voices[1].glide, Some((5.0, 4.0, GlideCurve::ConstantRate)));
+
+        // Legato mono notes glide on same voice.
+        sampler.set_mono(true);
+        sampler.set_legato(true);
+        sampler.note_on(0, 55, 127);
+        sampler.note_on(0, 62, 127);
+        let voice = sampler.voices.iter().find(|voice| voice.is_key_down()).unwrap();
+        assert_eq!((voice.get_active_note(), voice.glide), (Some(62), Some((-7.0, 4.0, GlideCurve::ConstantRate))));
+    }
+
+    #[test]
+    fn voice_stealing() {
+        let sampler = |voice_count: usize, steal_policy: StealPolicy| {
+            let mut sampler = Sampler::<DummySound, DummyVoice>::new();
+            sampler.add_sound(DummySound::new());
+            (0..voice_count).for_each(|_| sampler.add_voice(DummyVoice::new()));
+            sampler.set_reserved_voices(1);
+            sampler.set_steal_policy(steal_policy);
+            sampler
+        };
+
+        // Stolen voice fades out while note starts on reserved voice, fading voice is cut if no voice is free.
+        let mut oldest = sampler(3, StealPolicy::Oldest);
+        oldest.note_on(0, 48, 127);
+        oldest.note_on(0, 60, 127);
+        oldest.note_on(0, 62, 127);
+        assert!(oldest.voices[0].fading);
+        assert_eq!(oldest.voices[2].get_active_note(), Some(62));
+        oldest.note_on(0, 64, 127);
+        assert!(oldest.voices[1].fading);
+        assert_eq!(oldest.voices[0].get_active_note(), Some(64));
+        assert!(!oldest.voices[0].fading);
+
+        // Quietest voice is stolen.
+        let mut quietest = sampler(3, StealPolicy::Quietest);
+        quietest.note_on(0, 48, 127);
+        quietest.note_on(0, 60, 32);
+        quietest.note_on(0, 62, 127);
+        assert!(quietest.voices[1].fading);
+
+        // Voice playing same note (on other channel) is stolen.
+        let mut same_note = sampler(3, StealPolicy::SameNoteFirst);
+        same_note.note_on(0, 48, 127);
+        same_note.note_on(0, 60, 127);
+        same_note.note_on(1, 60, 127);
+        assert!(same_note.voices[1].fading);
+
+        // Highest and lowest notes are protected.
+        let mut protected = sampler(4, StealPolicy::Oldest);
+        protected.set_note_protection(true, true);
+        protected.note_on(0, 48, 127);
+        protected.note_on(0, 72, 127);
+        protected.note_on(0, 60, 127);
+        protected.note_on(0, 65, 127);
+        assert!(protected.voices[2].fading);
+        assert!(!protected.voices[0].fading && !protected.voices[1].fading);
+    }
+
+    #[test]
+    fn same_note() {
+        let sampler = |same_note: SameNoteBehavior| {
+            let mut sampler = Sampler::<DummySound, DummyVoice>::new();
+            sampler.add_sound(DummySound { same_note });
+            (0..4).for_each(|_| sampler.add_voice(DummyVoice::new()));
+            sampler
+        };
+
+        // Same note is released by default.
+        let mut release = sampler(SameNoteBehavior::default());
+        release.note_on(0, 60, 127);
+        release.note_on(0, 60, 127);
+        assert_eq!(release.voices.iter().filter(|voice| voice.is_playing()).count(), 1);
+
+        // Notes overlap without limit.
+        let mut overlap = sampler(SameNoteBehavior { max_voices: None, ..Default::default() });
+        (0..3).for_each(|_| overlap.note_on(0, 60, 127));
+        assert!(overlap.voices[..3].iter().all(|voice| voice.get_active_note() == Some(60)));
+
+        // Oldest voice is cut once note polyphony is reached.
+        let mut cut = sampler(SameNoteBehavior { cut: true, max_voices: Some(2), self_mask: false });
+        (0..3).for_each(|_| cut.note_on(0, 60, 127));
+        assert!(cut.voices[0].fading);
+        assert!(!cut.voices[1].fading && !cut.voices[2].fading);
+
+        // Louder voice is not masked by softer note.
+        let mut self_mask = sampler(SameNoteBehavior { cut: false, max_voices: Some(2), self_mask: true });
+        self_mask.note_on(0, 60, 127);
+        self_mask.note_on(0, 60, 32);
+        self_mask.note_on(0, 60, 64);
+        assert_eq!(self_mask.voices.iter().filter(|voice| voice.is_playing()).count(), 2);
+        assert_eq!(self_mask.voice_velocities[..2], [127, 64]);
+
+        // Layered sounds mask only their own voices.
+        let mut layered = sampler(SameNoteBehavior { cut: true, max_voices: Some(1), self_mask: false });
+        layered.add_sound(DummySound { same_note: SameNoteBehavior { max_voices: None, ..Default::default() } });
+        layered.note_on(0, 60, 127);
+        layered.note_on(0, 60, 127);
+        assert_eq!((layered.voice_sounds[0], layered.voice_sounds[1]), (0, 1));
+        assert!(layered.voices[0].fading);
+        assert!(!layered.voices[1].fading && layered.voices[1].get_active_note() == Some(60));
+        assert_eq!(layered.voices[2..].iter().filter(|voice| voice.is_playing() && !voice.fading).count(), 2);
+    }
 }
@@ -0,0 +1,165 @@
+use std::{
+    collections::HashMap,
+    io::{Error, ErrorKind, Read},
+};
+
+/// Decoded AIFF/AIFC file.
+#[derive(Debug)]
+pub struct AiffFile {
+    /// Base note from instrument chunk.
+    pub base_note: Option<u8>,
+
+    /// Channels in sample buffer.
+    pub channel_count: u16,
+
+    /// Sustain loop start and end in samples from instrument and marker chunks.
+    pub loop_region: Option<(usize, usize)>,
+
+    /// Interleaved samples.
+    pub sample_buffer: Box<[f32]>,
+
+    /// Sample rate in Hz.
+    pub sample_rate: f32,
+}
+impl AiffFile {
+    /// Reads AIFF or AIFC data, supports big-endian PCM (AIFC also little-endian PCM) and float samples.
+    pub fn read(mut reader: impl Read) -> Result<Self, Error> {
+        // Check FORM header.
+        let mut header = [0u8; 12];
+        reader.read_exact(&mut header)?;
+        if &header[0..4] != b"FORM" || !matches!(&header[8..12], b"AIFF" | b"AIFC") {
+            return Err(Error::new(ErrorKind::InvalidData, "Not an AIFF file."));
+        }
+
+        // Collect chunks of interest, order of chunks is not fixed.
+        let mut comm = None;
+        let mut sound_data = None;
+        let mut markers = HashMap::new();
+        let mut instrument = None;
+        let mut chunk_header = [0u8; 8];
+        while reader.read_exact(&mut chunk_header).is_ok() {
+            let size = u32::from_be_bytes(chunk_header[4..8].try_into().unwrap()) as u64;
+            let mut data = Vec::new();
+            (&mut reader).take(size + size % 2).read_to_end(&mut data)?; // Chunks are padded to even size.
+            data.truncate(size as usize);
+            match &chunk_header[0..4] {
+                b"COMM" => comm = Some(data),
+                b"SSND" => sound_data = Some(data),
+                b"MARK" => markers = AiffFile::parse_markers(&data),
+                b"INST" => instrument = Some(data),
+                _ => (),
+            }
+        }
+        let comm = comm.ok_or_else(|| Error::new(ErrorKind::InvalidData, "Missing COMM chunk."))?;
+        let sound_data = sound_data.ok_or_else(|| Error::new(ErrorKind::InvalidData, "Missing SSND chunk."))?;
+        if comm.len() < 18 || sound_data.len() < 8 {
+            return Err(Error::new(ErrorKind::InvalidData, "Invalid chunk size."));
+        }
+
+        // Parse common chunk, AIFC adds compression type.
+        let channel_count = u16::from_be_bytes([comm[0], comm[1]]);
+        let frame_count = u32::from_be_bytes(comm[2..6].try_into().unwrap()) as usize;
+        let bits_per_sample = u16::from_be_bytes([comm[6], comm[7]]);
+        let sample_rate = parse_extended(comm[8..18].try_into().unwrap()) as f32;
+        let compression = comm.get(18..22).unwrap_or(&b"NONE"[..]);
+        if channel_count == 0 {
+            return Err(Error::new(ErrorKind::InvalidData, "Invalid channel count."));
+        }
+
+        // Decode samples, sound data starts after offset and block size fields.
+        let offset = 8 + u32::from_be_bytes(sound_data[0..4].try_into().unwrap()) as usize;
+        let sound_data = sound_data.get(offset..).unwrap_or(&[]);
+        let sample_count = frame_count * channel_count as usize;
+        let sample_buffer: Box<[f32]> = match compression {
+            b"NONE" | b"twos" | b"sowt" => {
+                // PCM samples are left-justified, normalize by full byte width.
+                let bytes_per_sample = (bits_per_sample as usize).div_ceil(8);
+                if !(1..=4).contains(&bytes_per_sample) {
+                    return Err(Error::new(ErrorKind::InvalidData, "Unsupported sample size."));
+                }
+                let normalization_factor = f32::powi(2.0, 8 * bytes_per_sample as i32 - 1);
+                let little_endian = compression == b"sowt";
+                sound_data
+                    .chunks_exact(bytes_per_sample)
+                    .take(sample_count)
+                    .map(|bytes| {
+                        // Move sample bytes to the upper bytes of an i32 (keeps sign), then shift back down.
+                        let mut value = [0u8; 4];
+                        value[0..bytes_per_sample].copy_from_slice(bytes);
+                        if little_endian {
+                            value[0..bytes_per_sample].reverse();
+                        }
+                        (i32::from_be_bytes(value) >> (8 * (4 - bytes_per_sample))) as f32 / normalization_factor
+                    })
+                    .collect()
+            }
+            b"fl32" | b"FL32" => sound_data
+                .chunks_exact(4)
+                .take(sample_count)
+                .map(|bytes| f32::from_be_bytes(bytes.try_into().unwrap()))
+                .collect(),
+            b"fl64" | b"FL64" => sound_data
+                .chunks_exact(8)
+                .take(sample_count)
+                .map(|bytes| f64::from_be_bytes(bytes.try_into().unwrap()) as f32)
+                .collect(),
+            _ => return Err(Error::new(ErrorKind::InvalidData, "Unsupported AIFC compression type.")),
+        };
+
+        // Parse instrument chunk: base note and sustain loop (play mode, begin marker, end marker).
+        let mut base_note = None;
+        let mut loop_region = None;
+        if let Some(instrument) = instrument.filter(|instrument| instrument.len() >= 14) {
+            base_note = Some(instrument[0]).filter(|note| *note <= 127);
+            let play_mode = i16::from_be_bytes([instrument[8], instrument[9]]);
+            let begin = markers.get(&i16::from_be_bytes([instrument[10], instrument[11]]));
+            let end = markers.get(&i16::from_be_bytes([instrument[12], instrument[13]]));
+            if let (true, Some(begin), Some(end)) = (play_mode != 0, begin, end) {
+                loop_region = Some((*begin, *end)).filter(|(begin, end)| begin < end);
+            }
+        }
+
+        Ok(AiffFile { base_note, channel_count, loop_region, sample_buffer, sample_rate })
+    }
+
+    /// Parses marker chunk into a map of marker id to sample position.
+    fn parse_markers(data: &[u8]) -> HashMap<i16, usize> {
+        let mut markers = HashMap::new();
+        let marker_count = data.get(0..2).map_or(0, |count| u16::from_be_bytes([count[0], count[1]]));
+        let mut position = 2;
+        for _ in 0..marker_count {
+            // Marker: id, position, name as pascal string padded to even length.
+            let (marker, name_length) = match (data.get(position..position + 6), data.get(position + 6)) {
+                (Some(marker), Some(name_length)) => (marker, *name_length as usize),
+                _ => break,
+            };
+            let id = i16::from_be_bytes([marker[0], marker[1]]);
+            markers.insert(id, u32::from_be_bytes(marker[2..6].try_into().unwrap()) as usize);
+            position += 6 + (1 + name_length).div_ceil(2) * 2;
+        }
+        markers
+    }
+}
+
+/// Converts 80 bit IEEE 754 extended precision float (used for AIFF sample rates) to [f64].
+fn parse_extended(bytes: [u8; 10]) -> f64 {
+    let sign = if bytes[0] & 0x80 != 0 { -1.0 } else { 1.0 };
+    let exponent = (u16::from_be_bytes([bytes[0], bytes[1]]) & 0x7FFF) as i32;
+    let mantissa = u64::from_be_bytes(bytes[2..10].try_into().unwrap());
+    if exponent == 0 && mantissa == 0 {
+        return 0.0;
+    }
+    sign * mantissa as f64 * f64::powi(2.0, exponent - 16383 - 63)
+}
+
+/// Unit tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extended_sample_rate() {
+        assert_eq!(parse_extended([0x40, 0x0E, 0xBB, 0x80, 0, 0, 0, 0, 0, 0]), 48000.0);
+        assert_eq!(parse_extended([0x40, 0x0E, 0xAC, 0x44, 0, 0, 0, 0, 0, 0]), 44100.0);
+    }
+}
@@ -1,25 +1,67 @@
-use super::SamplerSound;
+use super::{
+    interpolation::PADDING_FRAMES, AiffFile, ExpressionModulation, Interpolation, MappedWav, SameNoteBehavior,
+    SampleData, SamplerSound,
+};
+use std::{
+    collections::HashMap,
+    io::{BufReader, Read},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
-/// Audio file sound for sampler.
-#[derive(Debug)]
+/// Audio file sound for sampler, clones share sample data.
+#[derive(Clone, Debug)]
 pub struct AudioFileSound {
     /// ADSR envelope in seconds.
     pub adsr: (f32, f32, f32, f32),
 
+    /// Pitch bend range down in cents (negative).
+    pub bend_down: f32,
+
+    /// Pitch bend range up in cents.
+    pub bend_up: f32,
+
     /// Channels in audio file / buffer.
     channel_count: u16,
 
     /// Duration in samples.
     pub duration_samples: usize,
 
+    /// Modulation by expression (pressure and timbre).
+    pub expression: ExpressionModulation,
+
+    /// Linear gain applied to sound.
+    pub gain: f32,
+
+    /// Interpolation used to read samples between frames.
+    pub interpolation: Interpolation,
+
+    /// Loop mode.
+    pub loop_mode: LoopMode,
+
+    /// Loop start and end in samples (end is exclusive).
+    pub loop_region: (usize, usize),
+
     /// Root midi note, lowest midi note, highest midi note, lowest velocity, highest velocity.
     pub midi_region: (u8, u8, u8, u8, u8),
 
-    /// Audio file sample buffer.
-    sample_buffer: Box<[f32]>,
+    /// Playback start in samples.
+    pub offset: usize,
+
+    /// Behavior when note is played again while still playing.
+    pub same_note: SameNoteBehavior,
+
+    /// Audio file samples, decoded or memory-mapped.
+    sample_data: Arc<SampleData>,
 
     /// Audio file sample rate.
     pub sample_rate: f32,
+
+    /// File that frames beyond sample buffer are streamed from (disk streaming).
+    stream_source: Option<PathBuf>,
+
+    /// Fine tuning in cents.
+    pub tune: f32,
 }
 impl AudioFileSound {
     /// Creates new audio file sound from WAV file.
@@ -28,56 +70,270 @@ impl AudioFileSound {
         midi_region: (u8, u8, u8, u8, u8),
         adsr: (f32, f32, f32, f32),
     ) -> Result<Self, hound::Error> {
-        // Read WAV samples into memory (disk streaming is planned for later).
+        let file = std::fs::File::open(file_path)?;
+        AudioFileSound::from_wav_reader(BufReader::new(file), midi_region, adsr)
+    }
+
+    /// Creates new audio file sound from WAV data, e.g. an entry of an archive.
+    pub fn from_wav_reader(
+        reader: impl Read,
+        midi_region: (u8, u8, u8, u8, u8),
+        adsr: (f32, f32, f32, f32),
+    ) -> Result<Self, hound::Error> {
+        // Read all WAV samples into memory.
+        let mut reader = hound::WavReader::new(reader)?;
+        let format = reader.spec();
+        let sample_buffer = read_wav_samples(&mut reader, usize::MAX)?;
+        Ok(AudioFileSound::from_samples(sample_buffer, format.channels, format.sample_rate as f32, midi_region, adsr))
+    }
+
+    /// Creates new audio file sound from WAV file, only the first frames are loaded (rest is streamed from disk).
+    pub fn from_wav_streamed(
+        file_path: &str,
+        preload_frames: usize,
+        midi_region: (u8, u8, u8, u8, u8),
+        adsr: (f32, f32, f32, f32),
+    ) -> Result<Self, hound::Error> {
         let mut reader = hound::WavReader::open(file_path)?;
         let format = reader.spec();
-        let sample_buffer: Box<[f32]> = match format.sample_format {
-            hound::SampleFormat::Float => reader.samples::<f32>().map(|s| s.unwrap()).collect(),
-            hound::SampleFormat::Int => {
-                let normalization_factor = f32::powi(2.0, format.bits_per_sample as i32 - 1);
-                reader.samples::<i32>().map(|s| s.unwrap() as f32 / normalization_factor).collect()
-            }
+        let duration_samples = reader.duration() as usize;
+        let sample_buffer = read_wav_samples(&mut reader, preload_frames.saturating_mul(format.channels as usize))?;
+        let mut sound =
+            AudioFileSound::from_samples(sample_buffer, format.channels, format.sample_rate as f32, midi_region, adsr);
+
+        // Stream only if file is longer than preloaded frames.
+        if sound.duration_samples < duration_samples {
+            sound.duration_samples = duration_samples;
+            sound.loop_region = (0, duration_samples);
+            sound.stream_source = Some(PathBuf::from(file_path));
+        }
+        Ok(sound)
+    }
+
+    /// Creates new audio file sound from memory-mapped WAV file, samples are converted on access.
+    pub fn from_wav_mapped(
+        file_path: &str,
+        midi_region: (u8, u8, u8, u8, u8),
+        adsr: (f32, f32, f32, f32),
+    ) -> Result<Self, std::io::Error> {
+        let mapped = MappedWav::open(file_path)?;
+        let mut sound =
+            AudioFileSound::from_samples(Box::new([]), mapped.channel_count, mapped.sample_rate, midi_region, adsr);
+        sound.duration_samples = mapped.frame_count;
+        sound.loop_region = (0, mapped.frame_count);
+        sound.sample_data = Arc::new(SampleData::Mapped(mapped));
+        Ok(sound)
+    }
+
+    /// Creates new audio file sound from FLAC file.
+    pub fn from_flac(
+        file_path: &str,
+        midi_region: (u8, u8, u8, u8, u8),
+        adsr: (f32, f32, f32, f32),
+    ) -> Result<Self, claxon::Error> {
+        let file = std::fs::File::open(file_path)?;
+        AudioFileSound::from_flac_reader(BufReader::new(file), midi_region, adsr)
+    }
+
+    /// Creates new audio file sound from FLAC data, e.g. an entry of an archive.
+    pub fn from_flac_reader(
+        reader: impl Read,
+        midi_region: (u8, u8, u8, u8, u8),
+        adsr: (f32, f32, f32, f32),
+    ) -> Result<Self, claxon::Error> {
+        // Decode all FLAC frames into memory, samples are returned interleaved.
+        let mut reader = claxon::FlacReader::new(reader)?;
+        let format = reader.streaminfo();
+        let normalization_factor = f32::powi(2.0, format.bits_per_sample as i32 - 1);
+        let sample_buffer: Box<[f32]> =
+            reader.samples().map(|s| s.map(|s| s as f32 / normalization_factor)).collect::<Result<_, _>>()?;
+        let channel_count = format.channels as u16;
+        Ok(AudioFileSound::from_samples(sample_buffer, channel_count, format.sample_rate as f32, midi_region, adsr))
+    }
+
+    /// Creates new audio file sound from AIFF/AIFC file, root note and loop are taken from the file if present.
+    pub fn from_aiff(
+        file_path: &str,
+        midi_region: (u8, u8, u8, u8, u8),
+        adsr: (f32, f32, f32, f32),
+    ) -> Result<Self, std::io::Error> {
+        let file = std::fs::File::open(file_path)?;
+        AudioFileSound::from_aiff_reader(BufReader::new(file), midi_region, adsr)
+    }
+
+    /// Creates new audio file sound from AIFF/AIFC data, e.g. an entry of an archive.
+    pub fn from_aiff_reader(
+        reader: impl Read,
+        midi_region: (u8, u8, u8, u8, u8),
+        adsr: (f32, f32, f32, f32),
+    ) -> Result<Self, std::io::Error> {
+        let aiff = AiffFile::read(reader)?;
+        let mut sound =
+            AudioFileSound::from_samples(aiff.sample_buffer, aiff.channel_count, aiff.sample_rate, midi_region, adsr);
+        sound.midi_region.0 = aiff.base_note.unwrap_or(midi_region.0);
+
+        // Loop continuously by default if file defines a loop (same as SFZ).
+        if let Some((loop_start, loop_end)) = aiff.loop_region {
+            sound.loop_mode = LoopMode::LoopContinuous;
+            sound.loop_region = (loop_start.min(sound.duration_samples), loop_end.min(sound.duration_samples));
+        }
+        Ok(sound)
+    }
+
+    /// Creates new audio file sound from MP3 file, encoder delay and padding are removed.
+    pub fn from_mp3(
+        file_path: &str,
+        midi_region: (u8, u8, u8, u8, u8),
+        adsr: (f32, f32, f32, f32),
+    ) -> Result<Self, symphonia::core::errors::Error> {
+        let file = std::fs::File::open(file_path)?;
+        AudioFileSound::from_mp3_reader(BufReader::new(file), midi_region, adsr)
+    }
+
+    /// Creates new audio file sound from MP3 data, e.g. an entry of an archive.
+    pub fn from_mp3_reader(
+        reader: impl Read,
+        midi_region: (u8, u8, u8, u8, u8),
+        adsr: (f32, f32, f32, f32),
+    ) -> Result<Self, symphonia::core::errors::Error> {
+        AudioFileSound::from_compressed_reader(reader, "mp3", midi_region, adsr)
+    }
+
+    /// Creates new audio file sound from Ogg Vorbis file, encoder delay and padding are removed.
+    pub fn from_vorbis(
+        file_path: &str,
+        midi_region: (u8, u8, u8, u8, u8),
+        adsr: (f32, f32, f32, f32),
+    ) -> Result<Self, symphonia::core::errors::Error> {
+        let file = std::fs::File::open(file_path)?;
+        AudioFileSound::from_vorbis_reader(BufReader::new(file), midi_region, adsr)
+    }
+
+    /// Creates new audio file sound from Ogg Vorbis data, e.g. an entry of an archive.
+    pub fn from_vorbis_reader(
+        reader: impl Read,
+        midi_region: (u8, u8, u8, u8, u8),
+        adsr: (f32, f32, f32, f32),
+    ) -> Result<Self, symphonia::core::errors::Error> {
+        AudioFileSound::from_compressed_reader(reader, "ogg", midi_region, adsr)
+    }
+
+    /// Creates new audio file sound from lossy compressed data via [symphonia].
+    fn from_compressed_reader(
+        mut reader: impl Read,
+        extension: &str,
+        midi_region: (u8, u8, u8, u8, u8),
+        adsr: (f32, f32, f32, f32),
+    ) -> Result<Self, symphonia::core::errors::Error> {
+        use symphonia::core::{
+            audio::SampleBuffer, codecs::DecoderOptions, errors::Error, formats::FormatOptions, io::MediaSourceStream,
+            meta::MetadataOptions, probe::Hint,
         };
 
-        // Add padding for linear interpolation.
-        let duration_samples = sample_buffer.len() / format.channels as usize;
-        let padding: Box<[f32]> = vec![0.0; format.channels as usize].into_boxed_slice();
-        let sample_buffer = [sample_buffer, padding].concat().into_boxed_slice();
+        // Compressed files are small, so read into memory to get a seekable source.
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let source = MediaSourceStream::new(Box::new(std::io::Cursor::new(data)), Default::default());
+
+        // Gapless mode trims encoder delay and padding, decoded samples then line up with the original audio.
+        let mut hint = Hint::new();
+        hint.with_extension(extension);
+        let format_options = FormatOptions { enable_gapless: true, ..Default::default() };
+        let probed =
+            symphonia::default::get_probe().format(&hint, source, &format_options, &MetadataOptions::default())?;
+        let mut format = probed.format;
+        let track = format.default_track().ok_or(Error::Unsupported("No audio track found."))?;
+        let track_id = track.id;
+        let mut decoder = symphonia::default::get_codecs().make(&track.codec_params, &DecoderOptions::default())?;
+
+        // Decode all packets into memory, samples are converted to interleaved f32.
+        let mut sample_buffer = Vec::new();
+        let mut packet_buffer: Option<SampleBuffer<f32>> = None;
+        let mut format_spec = None;
+        loop {
+            let packet = match format.next_packet() {
+                Ok(packet) => packet,
+                Err(Error::IoError(error)) if error.kind() == std::io::ErrorKind::UnexpectedEof => break,
+                Err(error) => return Err(error),
+            };
+            if packet.track_id() != track_id {
+                continue;
+            }
+            let decoded = decoder.decode(&packet)?;
+            let spec = *decoded.spec();
+            let packet_buffer = packet_buffer.get_or_insert_with(|| SampleBuffer::new(decoded.capacity() as u64, spec));
+            packet_buffer.copy_interleaved_ref(decoded);
+            sample_buffer.extend_from_slice(packet_buffer.samples());
+            format_spec = Some(spec);
+        }
+
+        let spec = format_spec.ok_or(Error::Unsupported("No audio packets found."))?;
+        let channel_count = spec.channels.count() as u16;
+        let sample_buffer = sample_buffer.into_boxed_slice();
+        Ok(AudioFileSound::from_samples(sample_buffer, channel_count, spec.rate as f32, midi_region, adsr))
+    }
+
+    /// Creates new audio file sound from decoded, interleaved samples.
+    fn from_samples(
+        sample_buffer: Box<[f32]>,
+        channel_count: u16,
+        sample_rate: f32,
+        midi_region: (u8, u8, u8, u8, u8),
+        adsr: (f32, f32, f32, f32),
+    ) -> Self {
+        // Add padding for interpolation.
+        let duration_samples = sample_buffer.len() / channel_count as usize;
+        let padding: Box<[f32]> = vec![0.0; PADDING_FRAMES * channel_count as usize].into_boxed_slice();
+        let sample_buffer = [padding.clone(), sample_buffer, padding].concat().into_boxed_slice();
 
-        // Create sound object.
-        let sound = AudioFileSound {
+        AudioFileSound {
             adsr,
-            channel_count: format.channels,
+            bend_down: -200.0,
+            bend_up: 200.0,
+            channel_count,
             duration_samples,
+            expression: ExpressionModulation::default(),
+            gain: 1.0,
+            interpolation: Interpolation::Linear,
+            loop_mode: LoopMode::NoLoop,
+            loop_region: (0, duration_samples),
             midi_region,
-            sample_buffer,
-            sample_rate: format.sample_rate as f32,
-        };
-        Ok(sound)
+            offset: 0,
+            same_note: SameNoteBehavior::default(),
+            sample_data: Arc::new(SampleData::Buffer(sample_buffer)),
+            sample_rate,
+            stream_source: None,
+            tune: 0.0,
+        }
     }
 
-    /// Returns stereo sample value at position (via linear interpolation).
+    /// Returns number of frames held in memory (all frames unless streamed).
+    pub fn preloaded_frames(&self) -> usize {
+        self.sample_data.frame_count(self.channel_count)
+    }
+
+    /// Returns sample data, shared between clones.
+    pub fn sample_data(&self) -> &Arc<SampleData> {
+        &self.sample_data
+    }
+
+    /// Returns file that frames beyond preloaded frames are streamed from, if streamed.
+    pub fn stream_source(&self) -> Option<&Path> {
+        self.stream_source.as_deref()
+    }
+
+    /// Returns stereo sample value at position (at original pitch).
     #[inline(always)]
     pub fn get_value(&self, sample_position: f32) -> (f32, f32) {
-        // Interpolation example: sample[2.25] = (0.75 * sample[2]) + (0.25 * sample[3]).
-        let index = sample_position as usize;
-        let alpha = sample_position - index as f32;
-        let inv_alpha = 1.0 - alpha;
-
-        // Samples are stored interleaved.
-        let interleaved_index_0 = (index + 0) * self.channel_count as usize;
-        let interleaved_index_1 = (index + 1) * self.channel_count as usize;
-
-        // Mirror left channel if mono, ignore channels beyond stereo.
-        let l = inv_alpha * self.sample_buffer[interleaved_index_0] + alpha * self.sample_buffer[interleaved_index_1];
-        let r = match self.channel_count {
-            1 => l,
-            _ => {
-                inv_alpha * self.sample_buffer[interleaved_index_0 + 1]
-                    + alpha * self.sample_buffer[interleaved_index_1 + 1]
-            }
-        };
-        (l, r)
+        self.get_value_at_rate(sample_position, 1.0)
+    }
+
+    /// Returns stereo sample value at position, played back with position increment (e.g. 2.0 one octave up).
+    #[inline(always)]
+    pub fn get_value_at_rate(&self, sample_position: f32, position_increment: f32) -> (f32, f32) {
+        // Mono is mirrored to both channels, channels beyond stereo are ignored.
+        self.interpolation
+            .interpolate(sample_position, position_increment, |index| self.sample_data.frame(index, self.channel_count))
     }
 }
 impl SamplerSound for AudioFileSound {
@@ -87,6 +343,68 @@ impl SamplerSound for AudioFileSound {
             && midi_velocity >= self.midi_region.3
             && midi_velocity <= self.midi_region.4
     }
+
+    fn resample(sounds: &mut [Arc<Self>], sample_rate: f32) {
+        // Sample data shared between sounds is converted once, streamed and mapped sounds are kept.
+        let mut resampled_data: HashMap<*const SampleData, Arc<SampleData>> = HashMap::new();
+        for sound in sounds.iter_mut() {
+            let samples = match sound.sample_data.samples(sound.channel_count) {
+                Some(samples) if sound.sample_rate != sample_rate && sound.stream_source.is_none() => samples,
+                _ => continue,
+            };
+            let sample_data = resampled_data.entry(Arc::as_ptr(&sound.sample_data)).or_insert_with(|| {
+                let samples =
+                    super::offline_resampler::resample(samples, sound.channel_count, sound.sample_rate, sample_rate);
+                let padding: Box<[f32]> = vec![0.0; PADDING_FRAMES * sound.channel_count as usize].into_boxed_slice();
+                Arc::new(SampleData::Buffer([padding.clone(), samples, padding].concat().into_boxed_slice()))
+            });
+
+            // Convert positions, so sound plays the same at new sample rate.
+            let ratio = sample_rate as f64 / sound.sample_rate as f64;
+            let convert = |position: usize| (position as f64 * ratio).round() as usize;
+            let frame_count = sample_data.frame_count(sound.channel_count);
+            *sound = Arc::new(AudioFileSound {
+                duration_samples: convert(sound.duration_samples).min(frame_count),
+                loop_region: (convert(sound.loop_region.0), convert(sound.loop_region.1).min(frame_count)),
+                offset: convert(sound.offset).min(frame_count),
+                sample_data: sample_data.clone(),
+                sample_rate,
+                ..sound.as_ref().clone()
+            });
+        }
+    }
+
+    fn same_note_behavior(&self) -> SameNoteBehavior {
+        self.same_note
+    }
+}
+
+/// Reads up to sample count interleaved samples from WAV reader, normalized to [-1.0, 1.0].
+pub(super) fn read_wav_samples<R: Read>(
+    reader: &mut hound::WavReader<R>,
+    sample_count: usize,
+) -> Result<Box<[f32]>, hound::Error> {
+    let format = reader.spec();
+    match format.sample_format {
+        hound::SampleFormat::Float => reader.samples::<f32>().take(sample_count).collect(),
+        hound::SampleFormat::Int => {
+            let normalization_factor = f32::powi(2.0, format.bits_per_sample as i32 - 1);
+            reader.samples::<i32>().take(sample_count).map(|s| s.map(|s| s as f32 / normalization_factor)).collect()
+        }
+    }
+}
+
+/// Loop mode of an audio file sound.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LoopMode {
+    /// Sample plays from start to end, no looping.
+    NoLoop,
+
+    /// Loop region repeats until the voice stops, including the release stage.
+    LoopContinuous,
+
+    /// Loop region repeats until note off, then sample plays to the end.
+    LoopSustain,
 }
 
 /// Unit tests.
@@ -133,6 +451,118 @@ mod tests {
         }
     }
 
+    #[test]
+    fn decodes_aiff() {
+        let test_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/test");
+        let wav = AudioFileSound::from_wav(
+            test_dir.join("test_sine.wav").to_str().unwrap(),
+            (48, 40, 50, 18, 120),
+            (0.02, 0.0, 0.0, 0.3),
+        )
+        .unwrap();
+        let aiff = AudioFileSound::from_aiff(
+            test_dir.join("test_sine.aiff").to_str().unwrap(),
+            (48, 40, 50, 18, 120),
+            (0.02, 0.0, 0.0, 0.3),
+        )
+        .unwrap();
+        assert_eq!(aiff.sample_rate, wav.sample_rate);
+        assert_eq!(aiff.sample_data, wav.sample_data); // Same 16 bit samples, including padding.
+        assert_eq!(aiff.midi_region, (29, 40, 50, 18, 120)); // Base note from instrument chunk.
+        assert_eq!(aiff.loop_mode, LoopMode::LoopContinuous);
+        assert_eq!(aiff.loop_region, (1200, 2400)); // Sustain loop markers.
+
+        // AIFC with 32 bit float samples (left channel only), no instrument chunk.
+        let aifc = AudioFileSound::from_aiff(
+            test_dir.join("test_sine_float.aifc").to_str().unwrap(),
+            (48, 40, 50, 18, 120),
+            (0.02, 0.0, 0.0, 0.3),
+        )
+        .unwrap();
+        assert_eq!(aifc.duration_samples, wav.duration_samples);
+        assert_eq!(aifc.midi_region.0, 48);
+        assert_eq!(aifc.loop_mode, LoopMode::NoLoop);
+        for i in 0..512 {
+            assert_eq!(aifc.get_value(i as f32).0, wav.get_value(i as f32).0);
+        }
+    }
+
+    #[test]
+    fn decodes_flac() {
+        let test_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/test");
+        let wav = AudioFileSound::from_wav(
+            test_dir.join("test_sine.wav").to_str().unwrap(),
+            (48, 40, 50, 18, 120),
+            (0.02, 0.0, 0.0, 0.3),
+        )
+        .unwrap();
+        let flac = AudioFileSound::from_flac(
+            test_dir.join("test_sine.flac").to_str().unwrap(),
+            (48, 40, 50, 18, 120),
+            (0.02, 0.0, 0.0, 0.3),
+        )
+        .unwrap();
+        assert_eq!(flac.duration_samples, wav.duration_samples);
+        assert_eq!(flac.sample_rate, wav.sample_rate);
+        assert_eq!(flac.sample_data, wav.sample_data); // Same 16 bit samples, including padding.
+
+        // 24 bit mono, left channel is mirrored.
+        let flac = AudioFileSound::from_flac(
+            test_dir.join("test_sine_mono_24.flac").to_str().unwrap(),
+            (48, 40, 50, 18, 120),
+            (0.02, 0.0, 0.0, 0.3),
+        )
+        .unwrap();
+        assert_eq!(flac.duration_samples, 4800);
+        for i in 0..512 {
+            let expected = f32::sin(2.0 * PI * 480.0 * i as f32 / flac.sample_rate);
+            let value = flac.get_value(i as f32);
+            assert!(
+                (value.0 - expected).abs() < 1e-5,
+                "Unexpected sample value at index {}: got {} instead of {}",
+                i,
+                value.0,
+                expected
+            );
+            assert_eq!(value.0, value.1);
+        }
+    }
+
+    #[test]
+    fn decodes_mp3() {
+        let test_file =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/test/test_silence.mp3").to_str().unwrap().to_string();
+        let sound = AudioFileSound::from_mp3(&test_file, (48, 40, 50, 18, 120), (0.02, 0.0, 0.0, 0.3)).unwrap();
+
+        // 10 frames (1152 samples each) minus decoder delay, encoder delay and padding stated in LAME tag.
+        assert_eq!(sound.sample_rate, 48000.0);
+        assert_eq!(sound.duration_samples, 10 * 1152 - (529 + 576) - (944 - 529));
+        assert!((0..sound.duration_samples).all(|i| sound.get_value(i as f32) == (0.0, 0.0)));
+    }
+
+    #[test]
+    fn decodes_vorbis() {
+        // Test sample taken from audrey crate (MIT/Apache 2.0): 1s 440hz sine on both channels, 44.1khz.
+        let test_file =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/test/test_sine_440.ogg").to_str().unwrap().to_string();
+        let sound = AudioFileSound::from_vorbis(&test_file, (48, 40, 50, 18, 120), (0.02, 0.0, 0.0, 0.3)).unwrap();
+        assert_eq!(sound.duration_samples, 44100); // No encoder delay or padding.
+
+        // Samples must line up with the original sine (lossy, but a shift by one sample exceeds tolerance).
+        for i in 0..sound.duration_samples {
+            let expected = f32::sin(2.0 * PI * 440.0 * i as f32 / sound.sample_rate);
+            let value = sound.get_value(i as f32);
+            assert!(
+                (value.0 - expected).abs() < 2e-2,
+                "Unexpected left sample value at index {}: got {} instead of {}",
+                i,
+                value.0,
+                expected
+            );
+            assert!((value.1 - expected).abs() < 2e-2);
+        }
+    }
+
     #[test]
     fn interpolates_samples() {
         let test_file =
@@ -153,4 +583,87 @@ mod tests {
         assert!((last_value_4.0 - expected.0).abs() < 1e-16);
         assert!((last_value_4.1 - expected.1).abs() < 1e-16);
     }
+
+    #[test]
+    fn preloads_frames() {
+        let test_file =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/test/test_sine.wav").to_str().unwrap().to_string();
+        let wav = AudioFileSound::from_wav(&test_file, (48, 40, 50, 18, 120), (0.02, 0.0, 0.0, 0.3)).unwrap();
+        let streamed =
+            AudioFileSound::from_wav_streamed(&test_file, 1000, (48, 40, 50, 18, 120), (0.02, 0.0, 0.0, 0.3)).unwrap();
+        assert_eq!(streamed.duration_samples, wav.duration_samples);
+        assert_eq!(streamed.loop_region, (0, wav.duration_samples));
+        assert_eq!(streamed.preloaded_frames(), 1000);
+        assert_eq!(streamed.stream_source(), Some(Path::new(&test_file)));
+        assert_eq!(wav.stream_source(), None);
+        for i in 0..1000 {
+            assert_eq!(streamed.get_value(i as f32), wav.get_value(i as f32));
+        }
+
+        // Nothing to stream if file is shorter than preloaded frames.
+        let streamed =
+            AudioFileSound::from_wav_streamed(&test_file, 100000, (48, 40, 50, 18, 120), (0.02, 0.0, 0.0, 0.3))
+                .unwrap();
+        assert_eq!(streamed.preloaded_frames(), wav.duration_samples);
+        assert_eq!(streamed.stream_source(), None);
+    }
+
+    #[test]
+    fn maps_wav() {
+        let test_file =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/test/test_sine.wav").to_str().unwrap().to_string();
+        let wav = AudioFileSound::from_wav(&test_file, (48, 40, 50, 18, 120), (0.02, 0.0, 0.0, 0.3)).unwrap();
+        let mapped = AudioFileSound::from_wav_mapped(&test_file, (48, 40, 50, 18, 120), (0.02, 0.0, 0.0, 0.3)).unwrap();
+        assert!(matches!(*mapped.sample_data, SampleData::Mapped(_)));
+        assert_eq!(mapped.duration_samples, wav.duration_samples);
+        assert_eq!(mapped.loop_region, wav.loop_region);
+        assert_eq!(mapped.sample_rate, wav.sample_rate);
+        assert_eq!(mapped.preloaded_frames(), wav.duration_samples);
+        for i in 0..wav.duration_samples {
+            // Last frame is interpolated with zero padding.
+            let position = i as f32 + 0.25;
+            assert_eq!(mapped.get_value(position), wav.get_value(position));
+        }
+    }
+
+    #[test]
+    fn interpolation_error() {
+        // Sine at 0.2 cycles per sample, played back a fifth up (0.3 cycles per sample).
+        let samples: Box<[f32]> = (0..4096).map(|i| f32::sin(2.0 * PI * 0.2 * i as f32)).collect();
+        let mut sound = AudioFileSound::from_samples(samples, 1, 48000.0, (48, 0, 127, 0, 127), (0.0, 0.0, 0.0, 0.0));
+        let mut rms_error = |interpolation| {
+            sound.interpolation = interpolation;
+            let errors: Vec<f32> = (0..1000)
+                .map(|i| 100.3 + i as f32 * 1.5)
+                .map(|position| sound.get_value_at_rate(position, 1.5).0 - f32::sin(2.0 * PI * 0.2 * position))
+                .collect();
+            f32::sqrt(errors.iter().map(|error| error * error).sum::<f32>() / errors.len() as f32)
+        };
+
+        let linear_error = rms_error(Interpolation::Linear);
+        let hermite_error = rms_error(Interpolation::Hermite);
+        let sinc_error = rms_error(Interpolation::Sinc);
+        assert!(hermite_error < 0.5 * linear_error);
+        assert!(sinc_error < 0.1 * hermite_error);
+    }
+
+    #[test]
+    fn suppresses_aliasing() {
+        // Sine at 0.3 cycles per sample, played back 2.5 times faster lies above Nyquist and should be removed.
+        let samples: Box<[f32]> = (0..4096).map(|i| f32::sin(2.0 * PI * 0.3 * i as f32)).collect();
+        let mut sound = AudioFileSound::from_samples(samples, 1, 48000.0, (48, 0, 127, 0, 127), (0.0, 0.0, 0.0, 0.0));
+        let mut rms = |interpolation| {
+            sound.interpolation = interpolation;
+            let values: Vec<f32> =
+                (0..1000).map(|i| sound.get_value_at_rate(100.0 + i as f32 * 2.5 + 0.3, 2.5).0).collect();
+            f32::sqrt(values.iter().map(|value| value * value).sum::<f32>() / values.len() as f32)
+        };
+
+        let linear_rms = rms(Interpolation::Linear);
+        let hermite_rms = rms(Interpolation::Hermite);
+        let sinc_rms = rms(Interpolation::Sinc);
+        assert!(linear_rms > 0.3); // Aliased to 0.25 cycles per sample.
+        assert!(hermite_rms > 0.3);
+        assert!(sinc_rms < 0.003); // About -50 dB.
+    }
 }
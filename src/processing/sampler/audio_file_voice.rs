@@ -1,4 +1,7 @@
-use super::{AudioFileSound, LinearAdsr, SamplerVoice};
+use super::{
+    interpolation, AudioFileSound, Expression, Glide, GlideCurve, LinearAdsr, LoopMode, PitchBend, SampleStream,
+    SampleStreamer, SamplerVoice,
+};
 use std::sync::Arc;
 
 /// Audio file voice for sampler.
@@ -10,13 +13,25 @@ pub struct AudioFileVoice {
     /// ADSR envelope.
     adsr: LinearAdsr,
 
+    /// Sample position where sound ends, used for internal processing.
+    end_position: f32,
+
+    /// Expression (pressure and timbre), applied to gain and position increment.
+    expression: Expression,
+
     /// Gain applied to sound.
     gain: f32,
 
+    /// Portamento glide, applied to position increment.
+    glide: Glide,
+
     /// Key down state.
     key_down: bool,
 
-    /// Position increment, used for internal processing.
+    /// Pitch bend, applied to position increment.
+    pitch_bend: PitchBend,
+
+    /// Position increment (without pitch bend and glide), used for internal processing.
     position_increment: f32,
 
     /// Voice priority.
@@ -27,6 +42,12 @@ pub struct AudioFileVoice {
 
     /// Sample rate in Hz.
     sample_rate: f32,
+
+    /// Stream for frames beyond preloaded frames of streamed sounds.
+    stream: Option<SampleStream>,
+
+    /// Sample position from which frames are pulled from stream, used for internal processing.
+    stream_position: f32,
 }
 impl AudioFileVoice {
     /// Creates new audio file voice.
@@ -34,16 +55,74 @@ impl AudioFileVoice {
         AudioFileVoice {
             active_sound: None,
             adsr: LinearAdsr::new(0.001, 0.1),
+            end_position: 0.0,
+            expression: Expression::new(),
             gain: 0.0,
+            glide: Glide::new(),
             key_down: false,
+            pitch_bend: PitchBend::new(),
             position_increment: 0.0,
             priority: 0,
             sample_position: 0.0,
             sample_rate: 44100.0,
+            stream: None,
+            stream_position: f32::INFINITY,
+        }
+    }
+
+    /// Creates new audio file voice that can play streamed sounds.
+    pub fn with_streamer(streamer: &SampleStreamer) -> Self {
+        AudioFileVoice { stream: Some(streamer.create_stream()), ..AudioFileVoice::new() }
+    }
+
+    /// Calculates position increment of sound played at MIDI note (without pitch bend).
+    fn note_increment(&self, sound: &AudioFileSound, midi_note: u8) -> f32 {
+        let semitones = midi_note as f32 - sound.midi_region.0 as f32 + sound.tune / 100.0;
+        f32::powf(2.0, semitones / 12.0) * (sound.sample_rate / self.sample_rate)
+    }
+
+    /// Starts stream of sound beyond preloaded frames at current sample position, unless voice already pulls from stream.
+    ///
+    /// Switch to stream before interpolation reads frames beyond preloaded frames at highest playback rate.
+    fn start_stream(&mut self) {
+        let (Some((sound, _)), Some(stream)) = (&self.active_sound, &mut self.stream) else {
+            return;
+        };
+        if sound.stream_source().is_none() {
+            return;
+        }
+        let preloaded_frames = sound.preloaded_frames();
+        let max_increment = self.position_increment
+            * self.pitch_bend.max_ratio()
+            * self.expression.max_ratio()
+            * self.glide.max_ratio();
+        let stream_position = preloaded_frames.saturating_sub(sound.interpolation.reach(max_increment));
+        if stream_position as f32 >= self.stream_position || self.sample_position >= self.stream_position {
+            return;
         }
+        let start_frame = (stream_position + 1).max(self.sample_position as usize);
+        let previous_frame = match start_frame.checked_sub(1) {
+            Some(index) if index < preloaded_frames => sound.get_value(index as f32),
+            _ => (0.0, 0.0),
+        };
+        stream.start(sound.clone(), start_frame, previous_frame);
+        self.stream_position = stream_position as f32;
     }
 }
 impl SamplerVoice<AudioFileSound> for AudioFileVoice {
+    fn change_note(&mut self, midi_note: u8) {
+        if let Some((sound, _)) = &self.active_sound {
+            self.position_increment = self.note_increment(sound, midi_note);
+        }
+        if let Some((_, note)) = &mut self.active_sound {
+            *note = midi_note;
+        }
+    }
+
+    fn fade_out(&mut self, fade_time: f32) {
+        self.adsr.fade_out(fade_time);
+    }
+
     fn get_active_note(&self) -> Option<u8> {
         if let Some((_, note)) = self.active_sound {
             Some(note)
@@ -52,6 +131,14 @@ impl SamplerVoice<AudioFileSound> for AudioFileVoice {
         }
     }
 
+    fn get_level(&self) -> f32 {
+        if self.is_playing() {
+            self.gain * self.adsr.envelope_gain()
+        } else {
+            0.0
+        }
+    }
+
     fn get_priority(&self) -> u32 {
         self.priority
     }
@@ -64,23 +151,49 @@ impl SamplerVoice<AudioFileSound> for AudioFileVoice {
         self.active_sound.is_some()
     }
 
+    fn is_releasing(&self) -> bool {
+        self.is_playing() && self.adsr.is_releasing()
+    }
+
     fn render(&mut self, buffer: &mut [f32]) {
         if let Some(sound) = &self.active_sound {
             // Sampler expects stereo.
             for frame in buffer.chunks_mut(2) {
-                // Get sample.
-                let envelope_gain = self.adsr.next_sample();
-                let sample = sound.0.get_value(self.sample_position);
+                // Get sample, from stream beyond preloaded frames (streamed frames are interpolated linearly).
+                let (expression_gain, expression_ratio) = self.expression.next();
+                let envelope_gain = self.adsr.next_sample() * expression_gain;
+                let pitch_ratio = self.pitch_bend.next_ratio() * self.glide.next_ratio() * expression_ratio;
+                let position_increment = self.position_increment * pitch_ratio;
+                let sample = if self.sample_position < self.stream_position {
+                    Some(sound.0.get_value_at_rate(self.sample_position, position_increment))
+                } else {
+                    self.stream.as_mut().and_then(|stream| stream.get_value(self.sample_position))
+                };
 
-                // Mix sample into output buffer.
-                frame[0] += sample.0 * envelope_gain * self.gain;
-                frame[1] += sample.1 * envelope_gain * self.gain;
+                // On buffer underrun, output silence and keep position until stream catches up.
+                if let Some(sample) = sample {
+                    // Mix sample into output buffer.
+                    frame[0] += sample.0 * envelope_gain * self.gain;
+                    frame[1] += sample.1 * envelope_gain * self.gain;
 
-                // Advance sample position, possibly stop note if reached end of sample.
-                self.sample_position += self.position_increment;
-                if self.sample_position > sound.0.duration_samples as f32 {
-                    self.stop_note(0.0, false);
-                    break;
+                    // Advance sample position, wrap around loop region or stop note if reached end of sample.
+                    // Streamed frames can only be pulled once, so loops must lie within preloaded frames.
+                    self.sample_position += position_increment;
+                    let (loop_start, loop_end) = (sound.0.loop_region.0 as f32, sound.0.loop_region.1 as f32);
+                    let looping = match sound.0.loop_mode {
+                        LoopMode::NoLoop => false,
+                        LoopMode::LoopContinuous => true,
+                        LoopMode::LoopSustain => !self.adsr.is_releasing(),
+                    };
+                    if looping && loop_end > loop_start && loop_end <= self.stream_position {
+                        while self.sample_position >= loop_end {
+                            self.sample_position -= loop_end - loop_start;
+                        }
+                    }
+                    if self.sample_position >= self.end_position {
+                        self.stop_note(0.0, false);
+                        break;
+                    }
                 }
 
                 // Stop note after envelope finished release stage.
@@ -93,25 +206,68 @@ impl SamplerVoice<AudioFileSound> for AudioFileVoice {
     }
 
     fn reset(&mut self, sample_rate: f32, _max_buffer_size: usize) {
-        self.active_sound = None;
+        self.stop_note(0.0, false);
         self.adsr.reset(sample_rate);
+        self.expression.reset(sample_rate);
+        self.glide.reset(sample_rate);
+        self.pitch_bend.reset(sample_rate);
+        interpolation::sinc_table(); // Allocate table here, not on audio thread.
         self.sample_rate = sample_rate;
         // Other parameters will be reset on note start.
     }
 
+    fn set_channel_pressure(&mut self, pressure: f32) {
+        self.expression.set_channel_pressure(pressure);
+    }
+
     fn set_key_down(&mut self, key_down: bool) {
         self.key_down = key_down;
     }
 
+    fn set_note_pressure(&mut self, pressure: f32) {
+        self.expression.set_note_pressure(pressure);
+    }
+
+    fn set_pitch_bend(&mut self, bend: f32, bend_range: Option<f32>) {
+        self.pitch_bend.set_bend(bend, bend_range);
+    }
+
+    fn set_release_scale(&mut self, release_scale: f32) {
+        self.adsr.set_release_scale(release_scale);
+    }
+
+    fn set_timbre(&mut self, timbre: f32) {
+        self.expression.set_timbre(timbre);
+    }
+
     fn start_note(&mut self, midi_note: u8, velocity: f32, sound: Arc<AudioFileSound>, initial_priority: u32) {
         self.adsr.set_parameters(sound.adsr.0, sound.adsr.3);
+        self.adsr.set_release_scale(1.0);
         self.adsr.note_on();
-        self.gain = velocity / 4.0; // TODO
-        self.position_increment = f32::powf(2.0, (midi_note as f32 - sound.midi_region.0 as f32) / 12.0)
-            * (sound.sample_rate / self.sample_rate);
-        self.sample_position = 0.0;
+        self.gain = sound.gain * velocity / 4.0; // TODO
+        self.position_increment = self.note_increment(&sound, midi_note);
+        self.sample_position = sound.offset as f32;
+        self.glide.stop();
+        self.pitch_bend.start(sound.bend_up, sound.bend_down);
+        self.expression.start(sound.expression);
+
+        // Stream frames that are not preloaded, voices without stream only play preloaded frames.
+        self.end_position = sound.duration_samples as f32;
+        self.stream_position = f32::INFINITY;
+        if sound.stream_source().is_some() && self.stream.is_none() {
+            self.end_position = self.end_position.min(sound.preloaded_frames() as f32);
+        }
         self.active_sound = Some((sound, midi_note));
         self.priority = initial_priority;
+        self.start_stream();
+    }
+
+    fn start_glide(&mut self, semitones: f32, time: f32, curve: GlideCurve) {
+        if self.active_sound.is_some() {
+            self.glide.start(semitones, time, curve);
+            // Gliding from higher note reads ahead faster, switch to stream earlier if still possible.
+            self.start_stream();
+        }
     }
 
     fn stop_note(&mut self, _velocity: f32, allow_tail: bool) {
@@ -119,6 +275,9 @@ impl SamplerVoice<AudioFileSound> for AudioFileVoice {
             self.adsr.note_off();
         } else {
             self.active_sound = None;
+            if let Some(stream) = &mut self.stream {
+                stream.stop();
+            }
         }
     }
 }
@@ -127,6 +286,7 @@ impl SamplerVoice<AudioFileSound> for AudioFileVoice {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::processing::{Interpolation, SamplerSound};
     use std::path::PathBuf;
 
     #[test]
@@ -148,6 +308,39 @@ mod tests {
         assert_eq!(voice.is_playing(), false);
     }
 
+    #[test]
+    fn loop_sound() {
+        let mut buffer: Box<[f32]> = vec![0.0; 512].into_boxed_slice();
+        let mut voice = AudioFileVoice::new();
+        let test_file =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/test/test_sine.wav").to_str().unwrap().to_string();
+        let mut sound = AudioFileSound::from_wav(&test_file, (48, 40, 60, 18, 120), (0.001, 0.0, 0.0, 0.1)).unwrap();
+        sound.duration_samples = 200;
+        sound.loop_mode = LoopMode::LoopContinuous;
+        sound.loop_region = (100, 200);
+        sound.offset = 50;
+        let sound = Arc::new(sound);
+        voice.reset(sound.sample_rate, buffer.len());
+
+        voice.start_note(48, 1.0, sound.clone(), 0);
+        voice.render(&mut buffer);
+        assert!(voice.is_playing()); // Rendered 256 samples, beyond end of sound.
+
+        for i in (128..buffer.len()).step_by(2) {
+            // Skip adsr attack, stereo voice. Loop end (200) wraps around to loop start (100).
+            let position = 50 + i / 2;
+            let position = if position < 200 { position } else { 100 + (position - 200) % 100 };
+            let value = sound.get_value(position as f32);
+            assert!(
+                (buffer[i] - value.0 / 4.0).abs() < 1e-6,
+                "Unexpected buffer value at index {}: got {} instead of {}",
+                i,
+                buffer[i],
+                value.0
+            );
+        }
+    }
+
     #[test]
     fn render_sound() {
         let mut buffer: Box<[f32]> = vec![0.0; 2048].into_boxed_slice();
@@ -180,4 +373,123 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn play_resampled_sound() {
+        let mut buffer: Box<[f32]> = vec![0.0; 2048].into_boxed_slice();
+        let mut voice = AudioFileVoice::new();
+        let test_file =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/test/test_sine.wav").to_str().unwrap().to_string();
+        let sound = AudioFileSound::from_wav(&test_file, (48, 40, 60, 18, 120), (0.001, 0.0, 0.0, 0.1)).unwrap();
+        let mut sounds = [Arc::new(sound)];
+        AudioFileSound::resample(&mut sounds, 44100.0);
+        voice.reset(44100.0, buffer.len());
+
+        // Root note plays converted samples unchanged.
+        voice.start_note(48, 1.0, sounds[0].clone(), 0);
+        voice.render(&mut buffer);
+        let samples = sounds[0].sample_data().samples(2).unwrap();
+        for i in 256..buffer.len() {
+            // Skip adsr attack.
+            assert_eq!(buffer[i], samples[i] / 4.0);
+        }
+    }
+
+    #[test]
+    fn bend_pitch() {
+        let mut buffer: Box<[f32]> = vec![0.0; 4000].into_boxed_slice();
+        let mut voice = AudioFileVoice::new();
+        let test_file =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/test/test_sine.wav").to_str().unwrap().to_string();
+        let mut sound = AudioFileSound::from_wav(&test_file, (48, 40, 60, 18, 120), (0.001, 0.0, 0.0, 0.1)).unwrap();
+        sound.bend_up = 1200.0;
+        let sound = Arc::new(sound);
+        voice.reset(sound.sample_rate, buffer.len());
+
+        // Full bend up doubles play rate after short smoothing.
+        voice.start_note(48, 1.0, sound.clone(), 0);
+        voice.set_pitch_bend(1.0, None);
+        voice.render(&mut buffer);
+        let position = voice.sample_position;
+        assert!(position > 2000.0 && position < 4000.0);
+        voice.render(&mut buffer);
+        assert!((voice.sample_position - position - 4000.0).abs() < 1.0);
+
+        // Bend range override.
+        voice.set_pitch_bend(-1.0, Some(12.0));
+        voice.start_note(48, 1.0, sound, 0);
+        voice.render(&mut buffer);
+        assert_eq!(voice.sample_position, 1000.0);
+    }
+
+    #[test]
+    fn stream_sound() {
+        let mut buffer: Box<[f32]> = vec![0.0; 2048].into_boxed_slice();
+        let streamer = SampleStreamer::new();
+        let mut voice = AudioFileVoice::with_streamer(&streamer);
+        let test_file =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/test/test_sine.wav").to_str().unwrap().to_string();
+        let sound = AudioFileSound::from_wav(&test_file, (48, 40, 60, 18, 120), (0.001, 0.0, 0.0, 0.1)).unwrap();
+        let streamed =
+            AudioFileSound::from_wav_streamed(&test_file, 256, (48, 40, 60, 18, 120), (0.001, 0.0, 0.0, 0.1)).unwrap();
+        voice.reset(sound.sample_rate, buffer.len());
+
+        voice.start_note(48, 1.0, Arc::new(streamed), 0);
+        std::thread::sleep(std::time::Duration::from_millis(200)); // Give reader thread time to fill buffer.
+        voice.render(&mut buffer);
+        assert_eq!(streamer.underrun_count(), 0);
+
+        for i in (128..buffer.len()).step_by(2) {
+            // Skip adsr attack, stereo voice. Frames beyond 256 are streamed.
+            let value = sound.get_value((i / 2) as f32);
+            assert!(
+                (buffer[i] - value.0 / 4.0).abs() < 1e-6,
+                "Unexpected buffer value at index {}: got {} instead of {}",
+                i,
+                buffer[i],
+                value.0
+            );
+            assert!((buffer[i + 1] - value.1 / 4.0).abs() < 1e-6);
+        }
+
+        // Voice without stream only plays preloaded frames.
+        let mut voice = AudioFileVoice::new();
+        let streamed =
+            AudioFileSound::from_wav_streamed(&test_file, 256, (48, 40, 60, 18, 120), (0.001, 0.0, 0.0, 0.1)).unwrap();
+        voice.reset(sound.sample_rate, buffer.len());
+        voice.start_note(48, 1.0, Arc::new(streamed), 0);
+        voice.render(&mut buffer);
+        assert!(!voice.is_playing());
+    }
+
+    #[test]
+    fn stream_glide() {
+        let mut buffer: Box<[f32]> = vec![0.0; 2048].into_boxed_slice();
+        let streamer = SampleStreamer::new();
+        let mut voice = AudioFileVoice::with_streamer(&streamer);
+        let test_file =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/test/test_sine.wav").to_str().unwrap().to_string();
+        let mut streamed =
+            AudioFileSound::from_wav_streamed(&test_file, 256, (48, 40, 60, 18, 120), (0.001, 0.0, 0.0, 0.1)).unwrap();
+        streamed.interpolation = Interpolation::Sinc;
+        let streamed = Arc::new(streamed);
+        voice.reset(streamed.sample_rate, buffer.len());
+
+        // Gliding down from an octave above reads ahead twice as fast, switch to stream earlier.
+        voice.start_note(48, 1.0, streamed.clone(), 0);
+        let stream_position = voice.stream_position;
+        voice.start_glide(12.0, 0.1, GlideCurve::ConstantTime);
+        assert!(voice.stream_position < stream_position);
+        assert!(streamed.interpolation.reach(2.0 * 1.1225) as f32 <= 256.0 - voice.stream_position);
+        std::thread::sleep(std::time::Duration::from_millis(200)); // Give reader thread time to fill buffer.
+        voice.render(&mut buffer);
+        assert_eq!(streamer.underrun_count(), 0);
+
+        // Voice already pulls from stream, glide keeps stream.
+        let stream_position = voice.stream_position;
+        voice.start_glide(12.0, 0.1, GlideCurve::ConstantTime);
+        assert_eq!(voice.stream_position, stream_position);
+        voice.render(&mut buffer);
+        assert!(voice.is_playing());
+    }
 }
@@ -0,0 +1,138 @@
+/// State of a MIDI channel, e.g. pitch bend, pressure, timbre, pedals and portamento.
+#[derive(Clone, Copy, Debug)]
+pub struct ChannelState {
+    /// Last started note, next note glides from it with portamento.
+    pub last_note: Option<u8>,
+
+    /// Pitch bend, -1.0 to 1.0.
+    pub pitch_bend: f32,
+
+    /// Pitch bend range in semitones set via RPN 0, [None] to use bend range of sounds.
+    pub pitch_bend_range: Option<f32>,
+
+    /// Portamento state (CC 65).
+    pub portamento: bool,
+
+    /// Source note of next note glide set via portamento control (CC 84), used once.
+    pub portamento_control: Option<u8>,
+
+    /// Portamento time in seconds (per octave with constant rate curve), set via CC 5.
+    pub portamento_time: f32,
+
+    /// Channel pressure (aftertouch), 0.0 to 1.0.
+    pub pressure: f32,
+
+    /// Selected registered parameter number (MSB, LSB), 127 is null (no parameter selected).
+    registered_parameter: (u8, u8),
+
+    /// Soft pedal value (CC 67).
+    pub soft_pedal: u8,
+
+    /// Sostenuto pedal state (CC 66).
+    pub sostenuto_pedal: bool,
+
+    /// Sustain pedal value (CC 64), partial values are half-pedaling.
+    pub sustain_pedal: u8,
+
+    /// Timbre (CC 74), 0.0 to 1.0.
+    pub timbre: f32,
+}
+impl ChannelState {
+    /// Creates new channel state.
+    pub const fn new() -> Self {
+        ChannelState {
+            last_note: None,
+            pitch_bend: 0.0,
+            pitch_bend_range: None,
+            portamento: false,
+            portamento_control: None,
+            portamento_time: 0.1,
+            pressure: 0.0,
+            registered_parameter: (127, 127),
+            soft_pedal: 0,
+            sostenuto_pedal: false,
+            sustain_pedal: 0,
+            timbre: 0.0,
+        }
+    }
+
+    /// Returns selected registered parameter number (MSB, LSB).
+    pub fn registered_parameter(&self) -> (u8, u8) {
+        self.registered_parameter
+    }
+
+    /// Resets controllers (Reset All Controllers message): pitch bend, pressure, portamento switch and control and
+    /// registered parameter selection.
+    ///
+    /// Portamento time, pitch bend range and timbre (a sound controller) are kept, pedals are reset by sampler
+    /// (releasing held notes).
+    pub fn reset_controllers(&mut self) {
+        self.pitch_bend = 0.0;
+        self.portamento = false;
+        self.portamento_control = None;
+        self.pressure = 0.0;
+        self.registered_parameter = (127, 127);
+    }
+
+    /// Sets pitch bend from 14 bit MIDI value (8192 is center).
+    pub fn set_pitch_bend(&mut self, value: u16) {
+        let value = value.min(16383) as f32 - 8192.0;
+        self.pitch_bend = if value >= 0.0 { value / 8191.0 } else { value / 8192.0 };
+    }
+
+    /// Handles registered parameter controls (CC 6, 38, 98 to 101), returns false for other controls.
+    pub fn handle_parameter_control(&mut self, control: u8, value: u8) -> bool {
+        match control {
+            // Data entry MSB and LSB, pitch bend range is set in semitones and cents.
+            0x06 if self.registered_parameter == (0, 0) => {
+                let cents = self.pitch_bend_range.map_or(0.0, |range| range.fract());
+                self.pitch_bend_range = Some(value as f32 + cents);
+            }
+            0x26 if self.registered_parameter == (0, 0) => {
+                let semitones = self.pitch_bend_range.map_or(2.0, f32::trunc);
+                self.pitch_bend_range = Some(semitones + value.min(99) as f32 / 100.0);
+            }
+            0x06 | 0x26 => (), // Unsupported parameter.
+
+            // Non-registered parameters are not supported, deselect registered parameter.
+            0x62 | 0x63 => self.registered_parameter = (127, 127),
+            0x64 => self.registered_parameter.1 = value,
+            0x65 => self.registered_parameter.0 = value,
+            _ => return false,
+        }
+        true
+    }
+}
+
+/// Unit tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pitch_bend_range() {
+        let mut channel = ChannelState::new();
+        channel.set_pitch_bend(0);
+        assert_eq!(channel.pitch_bend, -1.0);
+        channel.set_pitch_bend(16383);
+        assert_eq!(channel.pitch_bend, 1.0);
+
+        // Data entry without selected parameter is ignored.
+        assert!(channel.handle_parameter_control(0x06, 12));
+        assert_eq!(channel.pitch_bend_range, None);
+
+        // RPN 0 sets pitch bend range.
+        channel.handle_parameter_control(0x65, 0);
+        channel.handle_parameter_control(0x64, 0);
+        channel.handle_parameter_control(0x06, 12);
+        channel.handle_parameter_control(0x26, 50);
+        assert_eq!(channel.pitch_bend_range, Some(12.5));
+
+        // Null RPN deselects parameter.
+        channel.handle_parameter_control(0x65, 127);
+        channel.handle_parameter_control(0x64, 127);
+        channel.handle_parameter_control(0x06, 2);
+        assert_eq!(channel.pitch_bend_range, Some(12.5));
+        assert!(!channel.handle_parameter_control(0x40, 127));
+    }
+}
@@ -0,0 +1,153 @@
+/// Smoothing time constant in seconds, avoids zipper noise of coarse controller steps.
+const SMOOTHING_TIME: f32 = 0.005;
+
+/// Modulation of a sound by expression: channel pressure, note pressure and timbre.
+///
+/// Sources match SFZ controllers 129 (channel aftertouch), 130 (polyphonic aftertouch) and 74 (timbre, MPE slide).
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ExpressionModulation {
+    /// Amplitude change in percent at full channel pressure, note pressure and timbre (`amplitude_oncc129/130/74`).
+    pub amplitude: (f32, f32, f32),
+
+    /// Pitch change in cents at full channel pressure, note pressure and timbre (`pitch_oncc129/130/74`).
+    pub pitch: (f32, f32, f32),
+}
+
+/// Expression of a voice, converted to smoothed gain and playback rate ratio.
+#[derive(Debug)]
+pub struct Expression {
+    /// Channel pressure, 0.0 to 1.0.
+    channel_pressure: f32,
+
+    /// Current gain.
+    gain: f32,
+
+    /// Modulation amounts of sound.
+    modulation: ExpressionModulation,
+
+    /// Pressure of playing note (polyphonic aftertouch), 0.0 to 1.0.
+    note_pressure: f32,
+
+    /// Current playback rate ratio.
+    ratio: f32,
+
+    /// Smoothing coefficient per sample.
+    smoothing: f32,
+
+    /// Gain that is approached.
+    target_gain: f32,
+
+    /// Playback rate ratio that is approached.
+    target_ratio: f32,
+
+    /// Timbre, 0.0 to 1.0.
+    timbre: f32,
+}
+impl Expression {
+    /// Creates new expression (no pressure, no modulation).
+    pub fn new() -> Self {
+        Expression {
+            channel_pressure: 0.0,
+            gain: 1.0,
+            modulation: ExpressionModulation::default(),
+            note_pressure: 0.0,
+            ratio: 1.0,
+            smoothing: 1.0,
+            target_gain: 1.0,
+            target_ratio: 1.0,
+            timbre: 0.0,
+        }
+    }
+
+    /// Returns playback rate ratio at full expression.
+    pub fn max_ratio(&self) -> f32 {
+        let (channel_pressure, note_pressure, timbre) = self.modulation.pitch;
+        let cents = channel_pressure.max(0.0) + note_pressure.max(0.0) + timbre.max(0.0);
+        f32::powf(2.0, cents / 1200.0)
+    }
+
+    /// Returns next gain and playback rate ratio, call once per sample.
+    #[inline(always)]
+    pub fn next(&mut self) -> (f32, f32) {
+        self.gain += self.smoothing * (self.target_gain - self.gain);
+        self.ratio += self.smoothing * (self.target_ratio - self.ratio);
+        (self.gain, self.ratio)
+    }
+
+    /// Resets smoothing for sample rate.
+    pub fn reset(&mut self, sample_rate: f32) {
+        self.smoothing = 1.0 - f32::exp(-1.0 / (SMOOTHING_TIME * sample_rate));
+        (self.gain, self.ratio) = (self.target_gain, self.target_ratio);
+    }
+
+    /// Sets channel pressure (0.0 to 1.0).
+    pub fn set_channel_pressure(&mut self, pressure: f32) {
+        self.channel_pressure = pressure;
+        self.update_targets();
+    }
+
+    /// Sets pressure of playing note (0.0 to 1.0).
+    pub fn set_note_pressure(&mut self, pressure: f32) {
+        self.note_pressure = pressure;
+        self.update_targets();
+    }
+
+    /// Sets timbre (0.0 to 1.0).
+    pub fn set_timbre(&mut self, timbre: f32) {
+        self.timbre = timbre;
+        self.update_targets();
+    }
+
+    /// Starts note with modulation amounts of sound, note pressure is cleared and current expression applies without smoothing.
+    pub fn start(&mut self, modulation: ExpressionModulation) {
+        self.modulation = modulation;
+        self.note_pressure = 0.0;
+        self.update_targets();
+        (self.gain, self.ratio) = (self.target_gain, self.target_ratio);
+    }
+
+    /// Calculates target gain and ratio.
+    fn update_targets(&mut self) {
+        let modulate = |amounts: (f32, f32, f32)| {
+            amounts.0 * self.channel_pressure + amounts.1 * self.note_pressure + amounts.2 * self.timbre
+        };
+        self.target_gain = (1.0 + modulate(self.modulation.amplitude) / 100.0).max(0.0);
+        self.target_ratio = f32::powf(2.0, modulate(self.modulation.pitch) / 1200.0);
+    }
+}
+
+/// Unit tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn modulate_gain_and_pitch() {
+        let mut expression = Expression::new();
+        expression.reset(1000.0);
+        expression.set_channel_pressure(0.5);
+        expression.start(ExpressionModulation { amplitude: (-100.0, 50.0, 0.0), pitch: (0.0, 1200.0, 0.0) });
+        assert_eq!(expression.next(), (0.5, 1.0));
+        assert!((expression.max_ratio() - 2.0).abs() < 1e-6);
+
+        // Note pressure is smoothed.
+        expression.set_note_pressure(1.0);
+        let (gain, ratio) = expression.next();
+        assert!(gain > 0.5 && gain < 1.0);
+        assert!(ratio > 1.0 && ratio < 2.0);
+        (0..100).for_each(|_| _ = expression.next());
+        let (gain, ratio) = expression.next();
+        assert!((gain - 1.0).abs() < 1e-4);
+        assert!((ratio - 2.0).abs() < 1e-4);
+
+        // Gain doesn't go negative, new notes start without note pressure.
+        expression.set_channel_pressure(1.0);
+        expression.start(ExpressionModulation { amplitude: (-200.0, 0.0, 0.0), pitch: (0.0, 1200.0, 0.0) });
+        assert_eq!(expression.next(), (0.0, 1.0));
+
+        // Timbre adds to pitch.
+        expression.set_timbre(0.5);
+        expression.start(ExpressionModulation { amplitude: (0.0, 0.0, 0.0), pitch: (0.0, 0.0, 2400.0) });
+        assert!((expression.next().1 - 2.0).abs() < 1e-6);
+    }
+}
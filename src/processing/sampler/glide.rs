@@ -0,0 +1,119 @@
+/// Portamento curve, decides how long a glide takes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GlideCurve {
+    /// Glide takes portamento time per octave, wider intervals take longer.
+    ConstantRate,
+
+    /// Glide takes portamento time regardless of interval.
+    #[default]
+    ConstantTime,
+}
+
+/// Portamento mode, decides which notes glide.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PortamentoMode {
+    /// Every note glides from previous note.
+    #[default]
+    Always,
+
+    /// Only notes played while previous note is held glide.
+    LegatoOnly,
+}
+
+/// Portamento glide of a voice, pitch slides linearly (in semitones) from previous note to current note.
+#[derive(Debug)]
+pub struct Glide {
+    /// Pitch offset from current note in semitones.
+    offset: f32,
+
+    /// Offset change per sample in semitones.
+    offset_delta: f32,
+
+    /// Remaining samples of glide.
+    remaining_samples: u32,
+
+    /// Sample rate in Hz.
+    sample_rate: f32,
+}
+impl Glide {
+    /// Creates new glide (no pitch offset).
+    pub fn new() -> Self {
+        Glide { offset: 0.0, offset_delta: 0.0, remaining_samples: 0, sample_rate: 44100.0 }
+    }
+
+    /// Returns highest playback rate ratio of remaining glide.
+    pub fn max_ratio(&self) -> f32 {
+        f32::powf(2.0, self.offset.max(0.0) / 12.0)
+    }
+
+    /// Returns next playback rate ratio, call once per sample.
+    #[inline(always)]
+    pub fn next_ratio(&mut self) -> f32 {
+        if self.remaining_samples == 0 {
+            return 1.0;
+        }
+        let ratio = f32::powf(2.0, self.offset / 12.0);
+        self.remaining_samples -= 1;
+        self.offset = if self.remaining_samples > 0 { self.offset - self.offset_delta } else { 0.0 };
+        ratio
+    }
+
+    /// Resets glide for sample rate.
+    pub fn reset(&mut self, sample_rate: f32) {
+        self.stop();
+        self.sample_rate = sample_rate;
+    }
+
+    /// Starts glide from pitch offset in semitones within time in seconds, adds to offset of ongoing glide.
+    pub fn start(&mut self, semitones: f32, time: f32, curve: GlideCurve) {
+        self.offset += semitones;
+        let duration = match curve {
+            GlideCurve::ConstantRate => time * self.offset.abs() / 12.0,
+            GlideCurve::ConstantTime => time,
+        };
+        self.remaining_samples = (duration * self.sample_rate).round() as u32;
+        self.offset_delta = self.offset / self.remaining_samples.max(1) as f32;
+        if self.remaining_samples == 0 {
+            self.offset = 0.0;
+        }
+    }
+
+    /// Stops glide, pitch jumps to current note.
+    pub fn stop(&mut self) {
+        self.offset = 0.0;
+        self.remaining_samples = 0;
+    }
+}
+
+/// Unit tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glide_curves() {
+        let mut glide = Glide::new();
+        glide.reset(1000.0);
+        assert_eq!(glide.next_ratio(), 1.0);
+
+        // Constant time glides one octave down in 100 samples.
+        glide.start(12.0, 0.1, GlideCurve::ConstantTime);
+        assert!((glide.max_ratio() - 2.0).abs() < 1e-6);
+        assert!((glide.next_ratio() - 2.0).abs() < 1e-6);
+        (0..49).for_each(|_| _ = glide.next_ratio());
+        assert!((glide.next_ratio() - f32::sqrt(2.0)).abs() < 1e-4);
+        (0..49).for_each(|_| _ = glide.next_ratio());
+        assert_eq!(glide.next_ratio(), 1.0);
+
+        // Constant rate glides two octaves up in 200 samples.
+        glide.start(-24.0, 0.1, GlideCurve::ConstantRate);
+        assert_eq!(glide.max_ratio(), 1.0);
+        (0..199).for_each(|_| _ = glide.next_ratio());
+        assert!(glide.next_ratio() < 1.0);
+        assert_eq!(glide.next_ratio(), 1.0);
+
+        glide.start(12.0, 0.1, GlideCurve::ConstantTime);
+        glide.stop();
+        assert_eq!(glide.next_ratio(), 1.0);
+    }
+}
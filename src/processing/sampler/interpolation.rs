@@ -0,0 +1,117 @@
+use std::{f32::consts::PI, sync::OnceLock};
+
+/// Zero crossings of sinc kernel on each side (at original pitch).
+const SINC_ZERO_CROSSINGS: usize = 8;
+
+/// Highest pitch ratio the sinc kernel is widened for, above it some aliasing remains.
+const MAX_SINC_RATIO: usize = 4;
+
+/// Sinc kernel table entries per zero crossing.
+const SINC_TABLE_RESOLUTION: usize = 256;
+
+/// Frames of zero padding before and after decoded samples, covers the widest sinc kernel.
+pub const PADDING_FRAMES: usize = SINC_ZERO_CROSSINGS * MAX_SINC_RATIO;
+
+/// Interpolation used to read samples between frames.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Interpolation {
+    /// Linear interpolation of two frames, cheapest but aliases when pitching up.
+    #[default]
+    Linear,
+
+    /// Cubic Hermite (Catmull-Rom) interpolation of four frames.
+    Hermite,
+
+    /// Windowed sinc interpolation, kernel is widened to band-limit pitched up samples.
+    Sinc,
+}
+impl Interpolation {
+    /// Maps SFZ `sample_quality` (0 to 10) to interpolation: 0 and 1 are linear, 2 is Hermite, higher is sinc.
+    pub fn from_sample_quality(sample_quality: u8) -> Self {
+        match sample_quality {
+            0 | 1 => Interpolation::Linear,
+            2 => Interpolation::Hermite,
+            _ => Interpolation::Sinc,
+        }
+    }
+
+    /// Returns number of frames after position that are read at position increment.
+    pub fn reach(&self, position_increment: f32) -> usize {
+        match self {
+            Interpolation::Linear => 1,
+            Interpolation::Hermite => 2,
+            Interpolation::Sinc => {
+                (SINC_ZERO_CROSSINGS as f32 * position_increment.clamp(1.0, MAX_SINC_RATIO as f32)).ceil() as usize
+            }
+        }
+    }
+
+    /// Interpolates value at position from frames (index relative to first frame), frames outside are padding.
+    #[inline(always)]
+    pub fn interpolate(
+        &self,
+        sample_position: f32,
+        position_increment: f32,
+        frame: impl Fn(isize) -> (f32, f32),
+    ) -> (f32, f32) {
+        let index = sample_position as isize;
+        let alpha = sample_position - index as f32;
+        match self {
+            Interpolation::Linear => {
+                // Interpolation example: sample[2.25] = (0.75 * sample[2]) + (0.25 * sample[3]).
+                let (frame_0, frame_1) = (frame(index), frame(index + 1));
+                let inv_alpha = 1.0 - alpha;
+                (inv_alpha * frame_0.0 + alpha * frame_1.0, inv_alpha * frame_0.1 + alpha * frame_1.1)
+            }
+            Interpolation::Hermite => {
+                let frames = [frame(index - 1), frame(index), frame(index + 1), frame(index + 2)];
+                let hermite = |y: [f32; 4]| {
+                    let c1 = 0.5 * (y[2] - y[0]);
+                    let c2 = y[0] - 2.5 * y[1] + 2.0 * y[2] - 0.5 * y[3];
+                    let c3 = 0.5 * (y[3] - y[0]) + 1.5 * (y[1] - y[2]);
+                    ((c3 * alpha + c2) * alpha + c1) * alpha + y[1]
+                };
+                (hermite(frames.map(|frame| frame.0)), hermite(frames.map(|frame| frame.1)))
+            }
+            Interpolation::Sinc => {
+                // Lower cutoff by pitch ratio (kernel gets wider), so frequencies above output Nyquist are removed.
+                let ratio = position_increment.clamp(1.0, MAX_SINC_RATIO as f32);
+                let scale = SINC_TABLE_RESOLUTION as f32 / ratio;
+                let half_width = (SINC_ZERO_CROSSINGS as f32 * ratio).ceil() as isize;
+                let table = sinc_table();
+                let (mut value, mut weight_sum) = ((0.0, 0.0), 0.0);
+                for n in (index - half_width + 1)..=(index + half_width) {
+                    let table_position = (sample_position - n as f32).abs() * scale;
+                    let table_index = table_position as usize;
+                    if table_index + 1 >= table.len() {
+                        continue;
+                    }
+                    let table_alpha = table_position - table_index as f32;
+                    let weight = table[table_index] + table_alpha * (table[table_index + 1] - table[table_index]);
+                    let frame = frame(n);
+                    value = (value.0 + weight * frame.0, value.1 + weight * frame.1);
+                    weight_sum += weight;
+                }
+
+                // Normalize, so that kernel has unity gain at DC for all fractional positions.
+                (value.0 / weight_sum, value.1 / weight_sum)
+            }
+        }
+    }
+}
+
+/// Returns Blackman-windowed sinc kernel (one side), allocated on first call (so don't call this from audio thread).
+pub fn sinc_table() -> &'static [f32] {
+    static SINC_TABLE: OnceLock<Box<[f32]>> = OnceLock::new();
+    SINC_TABLE.get_or_init(|| {
+        (0..=SINC_ZERO_CROSSINGS * SINC_TABLE_RESOLUTION)
+            .map(|i| {
+                let x = i as f32 / SINC_TABLE_RESOLUTION as f32;
+                let sinc = if i == 0 { 1.0 } else { f32::sin(PI * x) / (PI * x) };
+                let phase = PI * x / SINC_ZERO_CROSSINGS as f32;
+                let window = 0.42 + 0.5 * f32::cos(phase) + 0.08 * f32::cos(2.0 * phase);
+                sinc * window
+            })
+            .collect()
+    })
+}
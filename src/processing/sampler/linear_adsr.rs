@@ -12,6 +12,9 @@ pub struct LinearAdsr {
     release: f32,
     release_delta: f32,
 
+    /// Release time scale, e.g. to lengthen release while half-pedaling.
+    release_scale: f32,
+
     /// Sample rate in Hz.
     sample_rate: f32,
 
@@ -27,6 +30,7 @@ impl LinearAdsr {
             envelope_gain: 0.0,
             release: 0.0,
             release_delta: 0.0,
+            release_scale: 1.0,
             sample_rate: 44100.0,
             stage: None,
         };
@@ -34,11 +38,27 @@ impl LinearAdsr {
         adsr
     }
 
+    /// Returns current envelope gain.
+    pub fn envelope_gain(&self) -> f32 {
+        self.envelope_gain
+    }
+
+    /// Fades out within fade time in seconds regardless of release (e.g. stolen voice), note off keeps fade.
+    pub fn fade_out(&mut self, fade_time: f32) {
+        let fade_delta = self.envelope_gain / (fade_time * self.sample_rate).max(1.0);
+        self.stage = Some(AdsrStage::FadeOut(fade_delta));
+    }
+
     /// Returns whether ADSR is active.
     pub fn is_active(&self) -> bool {
         self.stage.is_some()
     }
 
+    /// Returns whether ADSR is in release (or fade-out) stage.
+    pub fn is_releasing(&self) -> bool {
+        matches!(self.stage, Some(AdsrStage::Release | AdsrStage::FadeOut(_)))
+    }
+
     /// Returns next sample and advances ADSR state.
     pub fn next_sample(&mut self) -> f32 {
         if let Some(stage) = &self.stage {
@@ -46,8 +66,10 @@ impl LinearAdsr {
                 AdsrStage::Attack => {
                     self.envelope_gain = (self.envelope_gain + self.attack_delta).min(1.0);
                 }
-                AdsrStage::Release => {
-                    self.envelope_gain -= self.release_delta;
+                AdsrStage::Release | AdsrStage::FadeOut(_) => {
+                    let delta =
+                        if let AdsrStage::FadeOut(fade_delta) = stage { *fade_delta } else { self.release_delta };
+                    self.envelope_gain -= delta;
                     if self.envelope_gain < 0.0 {
                         self.envelope_gain = 0.0;
                         self.stage = None;
@@ -60,7 +82,9 @@ impl LinearAdsr {
 
     /// Note off, triggers envelope release.
     pub fn note_off(&mut self) {
-        self.stage = Some(AdsrStage::Release);
+        if !matches!(self.stage, Some(AdsrStage::FadeOut(_))) {
+            self.stage = Some(AdsrStage::Release);
+        }
     }
 
     /// Note on, triggers envelope attack.
@@ -77,7 +101,13 @@ impl LinearAdsr {
 
         // Precalculate deltas.
         self.attack_delta = 1.0 / (self.attack * self.sample_rate);
-        self.release_delta = 1.0 / (self.release * self.sample_rate);
+        self.release_delta = 1.0 / (self.release * self.release_scale * self.sample_rate);
+    }
+
+    /// Sets release time scale, applies to ongoing release.
+    pub fn set_release_scale(&mut self, release_scale: f32) {
+        self.release_scale = release_scale.max(0.001);
+        self.set_parameters(self.attack, self.release);
     }
 
     /// Resets internal parameters of the envelope.
@@ -93,6 +123,7 @@ impl LinearAdsr {
 #[derive(Debug)]
 enum AdsrStage {
     Attack,
+    FadeOut(f32),
     Release,
 }
 
@@ -131,4 +162,43 @@ mod tests {
         assert_eq!(steps, 500);
         assert_eq!(adsr.is_active(), false);
     }
+
+    #[test]
+    fn scale_release() {
+        let mut adsr = LinearAdsr::new(0.001, 0.5);
+        let mut steps = 0;
+        adsr.reset(1000.0);
+        adsr.note_on();
+        adsr.next_sample();
+        adsr.next_sample();
+
+        // Scaling during release changes remaining release time.
+        adsr.note_off();
+        (0..250).for_each(|_| _ = adsr.next_sample());
+        adsr.set_release_scale(2.0);
+        while adsr.next_sample() > 0.0 {
+            steps += 1
+        }
+        assert!((499..=500).contains(&steps));
+    }
+
+    #[test]
+    fn fade_out() {
+        let mut adsr = LinearAdsr::new(0.001, 0.5);
+        let mut steps = 0;
+        adsr.reset(1000.0);
+        adsr.note_on();
+        adsr.next_sample();
+        adsr.next_sample();
+
+        // Fade ignores release time and following note off.
+        adsr.fade_out(0.01);
+        adsr.note_off();
+        assert!(adsr.is_releasing());
+        while adsr.next_sample() > 0.0 {
+            steps += 1
+        }
+        assert!((9..=10).contains(&steps));
+        assert!(!adsr.is_active());
+    }
 }
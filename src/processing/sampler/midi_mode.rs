@@ -0,0 +1,92 @@
+/// MIDI channel mode (omni on/off, mono/poly), set by channel mode messages on the basic channel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MidiMode {
+    /// Number of channels played monophonically from basic channel on (0 is all), [None] in poly mode.
+    mono_channels: Option<u8>,
+
+    /// Whether messages of all channels are received.
+    omni: bool,
+}
+impl MidiMode {
+    /// Basic channel, channel mode messages are only received on this channel.
+    pub const BASIC_CHANNEL: u8 = 0;
+
+    /// Creates new mode, omni on and poly (mode 1).
+    pub const fn new() -> Self {
+        MidiMode { mono_channels: None, omni: true }
+    }
+
+    /// Handles channel mode messages (CC 124 to 127), returns false for other controls.
+    pub fn handle_mode_control(&mut self, control: u8, value: u8) -> bool {
+        match control {
+            0x7C => self.omni = false,
+            0x7D => self.omni = true,
+            0x7E => self.mono_channels = Some(value.min(16)),
+            0x7F => self.mono_channels = None,
+            _ => return false,
+        }
+        true
+    }
+
+    /// Returns whether notes are played monophonically.
+    pub fn is_mono(&self) -> bool {
+        self.mono_channels.is_some()
+    }
+
+    /// Returns whether omni is on (notes of all channels are treated alike).
+    pub fn is_omni(&self) -> bool {
+        self.omni
+    }
+
+    /// Sets mono mode (playing on all channels from basic channel on) or poly mode.
+    pub fn set_mono(&mut self, mono: bool) {
+        self.mono_channels = if mono { Some(0) } else { None };
+    }
+
+    /// Returns whether messages of channel are received.
+    ///
+    /// With omni off, poly mode receives basic channel only and mono mode receives one channel per voice.
+    pub fn receives(&self, channel: u8) -> bool {
+        let channel = channel & 0x0F;
+        match (self.omni, self.mono_channels) {
+            (true, _) => true,
+            (false, None) => channel == Self::BASIC_CHANNEL,
+            (false, Some(0)) => (Self::BASIC_CHANNEL..16).contains(&channel),
+            (false, Some(count)) => (Self::BASIC_CHANNEL..Self::BASIC_CHANNEL + count).contains(&channel),
+        }
+    }
+}
+
+/// Unit tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn switch_modes() {
+        let mut mode = MidiMode::new();
+        assert!(mode.receives(5));
+        assert!(!mode.is_mono());
+
+        // Omni off, poly (mode 3).
+        assert!(mode.handle_mode_control(0x7C, 0));
+        assert!(mode.receives(0));
+        assert!(!mode.receives(5));
+
+        // Omni off, mono on 4 channels (mode 4).
+        assert!(mode.handle_mode_control(0x7E, 4));
+        assert!(mode.is_mono());
+        assert!(mode.receives(3));
+        assert!(!mode.receives(4));
+
+        // Omni on, mono (mode 2).
+        assert!(mode.handle_mode_control(0x7D, 0));
+        assert!(mode.is_omni());
+        assert!(mode.receives(15));
+
+        // Poly (mode 1).
+        assert!(mode.handle_mode_control(0x7F, 0));
+        assert_eq!(mode, MidiMode::new());
+        assert!(!mode.handle_mode_control(0x7B, 0));
+    }
+}
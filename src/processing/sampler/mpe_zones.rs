@@ -0,0 +1,83 @@
+/// MPE zone layout, lower zone is managed on channel 1 (index 0) and upper zone on channel 16 (index 15).
+///
+/// Notes on member channels are expressed individually, messages on master channel apply to the whole zone.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MpeZones {
+    /// Member channels of lower zone (channels 2 and up), 0 if disabled.
+    lower_members: u8,
+
+    /// Member channels of upper zone (channels 15 and down), 0 if disabled.
+    upper_members: u8,
+}
+impl MpeZones {
+    /// Creates new zone layout, MPE is disabled.
+    pub const fn new() -> Self {
+        MpeZones { lower_members: 0, upper_members: 0 }
+    }
+
+    /// Configures zone of master channel (0 or 15) with member channel count, 0 disables zone (MPE configuration message).
+    ///
+    /// Other zone shrinks if zones overlap, returns false if channel is not a master channel.
+    pub fn configure(&mut self, master_channel: u8, member_count: u8) -> bool {
+        let member_count = member_count.min(15);
+        match master_channel {
+            0 => {
+                self.lower_members = member_count;
+                self.upper_members = self.upper_members.min(14u8.saturating_sub(member_count));
+            }
+            15 => {
+                self.upper_members = member_count;
+                self.lower_members = self.lower_members.min(14u8.saturating_sub(member_count));
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Returns whether channel is a member channel of a zone.
+    pub fn is_member(&self, channel: u8) -> bool {
+        self.master_channel(channel).is_some_and(|master_channel| master_channel != channel)
+    }
+
+    /// Returns master channel of zone that channel belongs to (as master or member), [None] if outside of zones.
+    pub fn master_channel(&self, channel: u8) -> Option<u8> {
+        if self.lower_members > 0 && channel <= self.lower_members {
+            Some(0)
+        } else if self.upper_members > 0 && channel >= 15 - self.upper_members {
+            Some(15)
+        } else {
+            None
+        }
+    }
+}
+
+/// Unit tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn configure_zones() {
+        let mut zones = MpeZones::new();
+        assert_eq!(zones.master_channel(0), None);
+
+        // Lower and upper zone.
+        assert!(zones.configure(0, 7));
+        assert!(zones.configure(15, 5));
+        assert!(!zones.configure(3, 5));
+        assert_eq!(zones.master_channel(0), Some(0));
+        assert!(zones.is_member(7) && !zones.is_member(0) && !zones.is_member(8));
+        assert_eq!(zones.master_channel(9), None);
+        assert_eq!(zones.master_channel(10), Some(15));
+        assert!(zones.is_member(10) && !zones.is_member(15));
+
+        // Overlapping zone shrinks other zone, 15 members disable other zone.
+        zones.configure(0, 12);
+        assert_eq!(zones.master_channel(13), Some(15));
+        assert!(zones.is_member(14));
+        zones.configure(0, 15);
+        assert_eq!(zones.master_channel(15), Some(0));
+        zones.configure(0, 0);
+        assert_eq!(zones.master_channel(1), None);
+    }
+}
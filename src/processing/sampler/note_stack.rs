@@ -0,0 +1,90 @@
+/// Maximum number of held notes, oldest note is dropped if exceeded.
+const MAX_HELD_NOTES: usize = 128;
+
+/// Note priority in mono mode, decides which of the held notes sounds.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NotePriority {
+    /// Highest held note sounds.
+    Highest,
+
+    /// Last pressed note sounds.
+    #[default]
+    Last,
+
+    /// Lowest held note sounds.
+    Lowest,
+}
+
+/// Held notes (channel, note, velocity) in order of key press, used in mono mode.
+#[derive(Debug)]
+pub struct NoteStack {
+    /// Held notes, last pressed note is on top.
+    notes: Vec<(u8, u8, u8)>,
+}
+impl NoteStack {
+    /// Creates new note stack.
+    pub fn new() -> Self {
+        NoteStack { notes: Vec::with_capacity(MAX_HELD_NOTES) }
+    }
+
+    /// Removes all notes.
+    pub fn clear(&mut self) {
+        self.notes.clear();
+    }
+
+    /// Pushes pressed note on top, replaces note if already held.
+    pub fn push(&mut self, channel: u8, note: u8, velocity: u8) {
+        self.remove(channel, note);
+        if self.notes.len() >= MAX_HELD_NOTES {
+            self.notes.remove(0);
+        }
+        self.notes.push((channel, note, velocity));
+    }
+
+    /// Removes released note, returns false if note was not held.
+    pub fn remove(&mut self, channel: u8, note: u8) -> bool {
+        match self.notes.iter().position(|held| (held.0, held.1) == (channel, note)) {
+            Some(index) => {
+                self.notes.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns held note that sounds with note priority, [None] if no note is held.
+    pub fn sounding_note(&self, priority: NotePriority) -> Option<(u8, u8, u8)> {
+        match priority {
+            NotePriority::Highest => self.notes.iter().max_by_key(|held| held.1).copied(),
+            NotePriority::Last => self.notes.last().copied(),
+            NotePriority::Lowest => self.notes.iter().rev().min_by_key(|held| held.1).copied(),
+        }
+    }
+}
+
+/// Unit tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn note_priority() {
+        let mut stack = NoteStack::new();
+        assert_eq!(stack.sounding_note(NotePriority::Last), None);
+        stack.push(0, 60, 100);
+        stack.push(0, 48, 90);
+        stack.push(0, 64, 80);
+        stack.push(0, 55, 70);
+        assert_eq!(stack.sounding_note(NotePriority::Highest), Some((0, 64, 80)));
+        assert_eq!(stack.sounding_note(NotePriority::Last), Some((0, 55, 70)));
+        assert_eq!(stack.sounding_note(NotePriority::Lowest), Some((0, 48, 90)));
+
+        // Released notes are removed, pressing again moves note on top.
+        assert!(stack.remove(0, 55));
+        assert!(!stack.remove(0, 55));
+        stack.push(0, 48, 127);
+        assert_eq!(stack.sounding_note(NotePriority::Last), Some((0, 48, 127)));
+        stack.clear();
+        assert_eq!(stack.sounding_note(NotePriority::Highest), None);
+    }
+}
@@ -0,0 +1,80 @@
+use rubato::{FftFixedInOut, Resampler};
+
+/// Input frames per resampler chunk.
+const CHUNK_FRAMES: usize = 1024;
+
+/// Converts interleaved samples between sample rates (rounded to Hz) using FFT-based, band-limited resampling.
+///
+/// Output is aligned with input (resampler delay is removed), allocates so don't call this from audio thread.
+pub fn resample(samples: &[f32], channel_count: u16, input_rate: f32, output_rate: f32) -> Box<[f32]> {
+    let channel_count = channel_count as usize;
+    let (input_rate, output_rate) = (input_rate.round() as usize, output_rate.round() as usize);
+    let input_frames = samples.len() / channel_count;
+    let output_frames = (input_frames * output_rate).div_ceil(input_rate);
+
+    // Chunks are multiples of smallest chunk of both rates, an even multiple makes reported delay exact.
+    let gcd = |mut a: usize, mut b: usize| {
+        while b != 0 {
+            (a, b) = (b, a % b);
+        }
+        a
+    };
+    let min_chunk_frames = input_rate / gcd(input_rate, output_rate);
+    let chunk_frames = CHUNK_FRAMES.div_ceil(2 * min_chunk_frames) * 2 * min_chunk_frames;
+    let mut resampler = FftFixedInOut::<f32>::new(input_rate, output_rate, chunk_frames, channel_count)
+        .expect("Invalid resampler configuration.");
+
+    // Resampler works on separate channels.
+    let input: Vec<Vec<f32>> = (0..channel_count)
+        .map(|channel| samples.iter().skip(channel).step_by(channel_count).copied().collect())
+        .collect();
+
+    // Feed all input, then zeros until delayed output is complete.
+    let delay = resampler.output_delay();
+    let mut output: Vec<Vec<f32>> = vec![Vec::with_capacity(delay + output_frames); channel_count];
+    let mut position = 0;
+    while output[0].len() < delay + output_frames {
+        let chunk_frames = resampler.input_frames_next();
+        let chunk: Vec<&[f32]> = input.iter().map(|channel| &channel[input_frames.min(position)..]).collect();
+        let resampled = if position + chunk_frames <= input_frames {
+            resampler.process(&chunk, None)
+        } else if position < input_frames {
+            resampler.process_partial(Some(&chunk), None)
+        } else {
+            resampler.process_partial(None::<&[&[f32]]>, None)
+        };
+        let resampled = resampled.expect("Resampling failed.");
+        output.iter_mut().zip(resampled).for_each(|(channel, resampled)| channel.extend(resampled));
+        position += chunk_frames;
+    }
+
+    // Interleave output without delay.
+    (delay..delay + output_frames).flat_map(|frame| output.iter().map(move |channel| channel[frame])).collect()
+}
+
+/// Unit tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    #[test]
+    fn resample_sine() {
+        // Stereo 1 kHz sine, right channel inverted.
+        let samples: Vec<f32> = (0..48000)
+            .flat_map(|i| {
+                let value = f32::sin(2.0 * PI * 1000.0 * i as f32 / 48000.0);
+                [value, -value]
+            })
+            .collect();
+        let resampled = resample(&samples, 2, 48000.0, 44100.0);
+        assert_eq!(resampled.len(), 44100 * 2);
+
+        // Output is aligned with input, edges are left out as resampler sees silence beyond them.
+        for (i, frame) in resampled.chunks(2).enumerate().skip(1000).take(42000) {
+            let expected = f32::sin(2.0 * PI * 1000.0 * i as f32 / 44100.0);
+            assert!((frame[0] - expected).abs() < 1e-3);
+            assert!((frame[1] + expected).abs() < 1e-3);
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use super::{LinearAdsr, OscillatorSound, SamplerVoice};
+use super::{Glide, GlideCurve, LinearAdsr, OscillatorSound, PitchBend, SamplerVoice};
 use std::{f32::consts::PI, sync::Arc};
 
 /// Oscillator voice for sampler.
@@ -12,15 +12,21 @@ pub struct OscillatorVoice {
     /// Gain applied to sound.
     gain: f32,
 
+    /// Portamento glide, applied to phase increment.
+    glide: Glide,
+
     /// Key down state.
     key_down: bool,
 
     /// Phase, used for internal processing.
     phase: f32,
 
-    /// Precalculated phase increment per sample, used for internal processing.
+    /// Precalculated phase increment per sample (without pitch bend and glide), used for internal processing.
     phase_increment: f32,
 
+    /// Pitch bend, applied to phase increment.
+    pitch_bend: PitchBend,
+
     /// Sample rate in Hz.
     sample_rate: f32,
 }
@@ -32,19 +38,33 @@ impl OscillatorVoice {
             active_sound: None,
             adsr: LinearAdsr::new(0.03, 0.1),
             gain: 0.0,
+            glide: Glide::new(),
             key_down: false,
             phase: 0.0,
             phase_increment: 0.0,
+            pitch_bend: PitchBend::new(),
             sample_rate: 44100.0,
         }
     }
 
-    /// Calculates phase increment.
-    fn update_phase_increment(&mut self, frequency: f32) {
+    /// Calculates phase increment of MIDI note.
+    fn update_phase_increment(&mut self, midi_note: u8) {
+        let frequency = 440.0 * f32::powf(2.0, (midi_note as f32 - 69.0) / 12.0);
         self.phase_increment = 2.0 * PI * frequency / self.sample_rate;
     }
 }
 impl SamplerVoice<OscillatorSound> for OscillatorVoice {
+    fn change_note(&mut self, midi_note: u8) {
+        if let Some((_, note)) = &mut self.active_sound {
+            *note = midi_note;
+            self.update_phase_increment(midi_note);
+        }
+    }
+
+    fn fade_out(&mut self, fade_time: f32) {
+        self.adsr.fade_out(fade_time);
+    }
+
     fn get_active_note(&self) -> Option<u8> {
         if let Some((_, note)) = self.active_sound {
             Some(note)
@@ -53,6 +73,14 @@ impl SamplerVoice<OscillatorSound> for OscillatorVoice {
         }
     }
 
+    fn get_level(&self) -> f32 {
+        if self.is_playing() {
+            self.gain * self.adsr.envelope_gain()
+        } else {
+            0.0
+        }
+    }
+
     fn get_priority(&self) -> u32 {
         0
     }
@@ -65,6 +93,10 @@ impl SamplerVoice<OscillatorSound> for OscillatorVoice {
         self.active_sound.is_some()
     }
 
+    fn is_releasing(&self) -> bool {
+        self.is_playing() && self.adsr.is_releasing()
+    }
+
     fn render(&mut self, buffer: &mut [f32]) {
         if let Some(sound) = &self.active_sound {
             // Sampler expects stereo.
@@ -72,7 +104,7 @@ impl SamplerVoice<OscillatorSound> for OscillatorVoice {
                 let envelope_gain = self.adsr.next_sample();
                 let sample = sound.0.get_value(self.phase) * self.gain * envelope_gain * 0.1; // TODO
                 frame.iter_mut().for_each(|s| *s += sample);
-                self.phase += self.phase_increment;
+                self.phase += self.phase_increment * self.pitch_bend.next_ratio() * self.glide.next_ratio();
                 while self.phase >= 2.0 * PI {
                     self.phase -= 2.0 * PI
                 }
@@ -89,21 +121,45 @@ impl SamplerVoice<OscillatorSound> for OscillatorVoice {
     fn reset(&mut self, sample_rate: f32, _max_buffer_size: usize) {
         self.active_sound = None;
         self.adsr.reset(sample_rate);
+        self.glide.reset(sample_rate);
+        self.pitch_bend.reset(sample_rate);
         self.sample_rate = sample_rate;
         // Other parameters will be reset on note start.
     }
 
+    fn set_channel_pressure(&mut self, _pressure: f32) {} // Oscillator sounds have no expression modulation.
+
     fn set_key_down(&mut self, key_down: bool) {
         self.key_down = key_down;
     }
 
+    fn set_note_pressure(&mut self, _pressure: f32) {}
+
+    fn set_pitch_bend(&mut self, bend: f32, bend_range: Option<f32>) {
+        self.pitch_bend.set_bend(bend, bend_range);
+    }
+
+    fn set_release_scale(&mut self, release_scale: f32) {
+        self.adsr.set_release_scale(release_scale);
+    }
+
+    fn set_timbre(&mut self, _timbre: f32) {}
+
     fn start_note(&mut self, midi_note: u8, velocity: f32, sound: Arc<OscillatorSound>, _initial_priority: u32) {
-        let frequency = 440.0 * f32::powf(2.0, (midi_note as f32 - 69.0) / 12.0);
         self.active_sound = Some((sound, midi_note));
+        self.adsr.set_release_scale(1.0);
         self.adsr.note_on();
         self.gain = velocity;
+        self.glide.stop();
         self.phase = 0.0;
-        self.update_phase_increment(frequency);
+        self.pitch_bend.start(200.0, -200.0);
+        self.update_phase_increment(midi_note);
+    }
+
+    fn start_glide(&mut self, semitones: f32, time: f32, curve: GlideCurve) {
+        if self.active_sound.is_some() {
+            self.glide.start(semitones, time, curve);
+        }
     }
 
     fn stop_note(&mut self, _velocity: f32, allow_tail: bool) {
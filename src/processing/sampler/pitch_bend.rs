@@ -0,0 +1,112 @@
+/// Smoothing time constant in seconds, avoids zipper noise of coarse pitch bend steps.
+const SMOOTHING_TIME: f32 = 0.005;
+
+/// Pitch bend of a voice, converted to a smoothed playback rate ratio.
+#[derive(Debug)]
+pub struct PitchBend {
+    /// Bend, -1.0 to 1.0.
+    bend: f32,
+
+    /// Bend range in semitones, overrides range of sound if set (e.g. via RPN 0).
+    bend_range: Option<f32>,
+
+    /// Current playback rate ratio.
+    ratio: f32,
+
+    /// Smoothing coefficient per sample.
+    smoothing: f32,
+
+    /// Bend up and bend down range of sound in cents.
+    sound_range: (f32, f32),
+
+    /// Playback rate ratio that is approached.
+    target_ratio: f32,
+}
+impl PitchBend {
+    /// Creates new pitch bend (centered, 2 semitones range).
+    pub fn new() -> Self {
+        PitchBend {
+            bend: 0.0,
+            bend_range: None,
+            ratio: 1.0,
+            smoothing: 1.0,
+            sound_range: (200.0, -200.0),
+            target_ratio: 1.0,
+        }
+    }
+
+    /// Returns playback rate ratio at full bend up.
+    pub fn max_ratio(&self) -> f32 {
+        let cents = self.bend_range.map_or(self.sound_range.0, |bend_range| bend_range * 100.0);
+        f32::powf(2.0, cents.max(0.0) / 1200.0)
+    }
+
+    /// Returns next playback rate ratio, call once per sample.
+    #[inline(always)]
+    pub fn next_ratio(&mut self) -> f32 {
+        self.ratio += self.smoothing * (self.target_ratio - self.ratio);
+        self.ratio
+    }
+
+    /// Resets smoothing for sample rate.
+    pub fn reset(&mut self, sample_rate: f32) {
+        self.smoothing = 1.0 - f32::exp(-1.0 / (SMOOTHING_TIME * sample_rate));
+        self.ratio = self.target_ratio;
+    }
+
+    /// Sets bend (-1.0 to 1.0) and bend range in semitones (overrides range of sound if set).
+    pub fn set_bend(&mut self, bend: f32, bend_range: Option<f32>) {
+        self.bend = bend;
+        self.bend_range = bend_range;
+        self.update_target_ratio();
+    }
+
+    /// Starts note with bend up and bend down range of sound in cents, current bend applies without smoothing.
+    pub fn start(&mut self, bend_up: f32, bend_down: f32) {
+        self.sound_range = (bend_up, bend_down);
+        self.update_target_ratio();
+        self.ratio = self.target_ratio;
+    }
+
+    /// Calculates target ratio, bend down range is negative (e.g. -200 cents).
+    fn update_target_ratio(&mut self) {
+        let (up, down) = match self.bend_range {
+            Some(bend_range) => (bend_range * 100.0, -bend_range * 100.0),
+            None => self.sound_range,
+        };
+        let cents = if self.bend >= 0.0 { self.bend * up } else { -self.bend * down };
+        self.target_ratio = f32::powf(2.0, cents / 1200.0);
+    }
+}
+
+/// Unit tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn smooth_bend() {
+        let mut pitch_bend = PitchBend::new();
+        pitch_bend.reset(1000.0);
+        pitch_bend.start(1200.0, -2400.0);
+        assert_eq!(pitch_bend.next_ratio(), 1.0);
+
+        // Full bend up reaches one octave after smoothing.
+        pitch_bend.set_bend(1.0, None);
+        let first_ratio = pitch_bend.next_ratio();
+        assert!(first_ratio > 1.0 && first_ratio < 1.5);
+        (0..100).for_each(|_| _ = pitch_bend.next_ratio());
+        assert!((pitch_bend.next_ratio() - 2.0).abs() < 1e-4);
+
+        // Bend down range of sound, new notes start without smoothing.
+        pitch_bend.set_bend(-0.5, None);
+        pitch_bend.start(1200.0, -2400.0);
+        assert!((pitch_bend.next_ratio() - 0.5).abs() < 1e-6);
+
+        // Range set via RPN overrides range of sound.
+        pitch_bend.set_bend(-1.0, Some(12.0));
+        pitch_bend.start(1200.0, -2400.0);
+        assert!((pitch_bend.next_ratio() - 0.5).abs() < 1e-6);
+        assert!((pitch_bend.max_ratio() - 2.0).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,196 @@
+use super::interpolation::PADDING_FRAMES;
+use std::{
+    fs::File,
+    io::{Error, ErrorKind},
+};
+
+/// Sample data of an audio file sound.
+#[derive(Debug)]
+pub enum SampleData {
+    /// Decoded, interleaved samples, with zero padding frames before and after (for interpolation).
+    Buffer(Box<[f32]>),
+
+    /// Memory-mapped WAV file, samples are converted on access.
+    Mapped(MappedWav),
+}
+impl SampleData {
+    /// Returns stereo frame at index, mirrors left channel if mono and ignores channels beyond stereo.
+    ///
+    /// Indices within padding frames before and after the samples are valid and return zero.
+    #[inline(always)]
+    pub fn frame(&self, index: isize, channel_count: u16) -> (f32, f32) {
+        match self {
+            SampleData::Buffer(buffer) => {
+                let interleaved_index = (index + PADDING_FRAMES as isize) as usize * channel_count as usize;
+                let l = buffer[interleaved_index];
+                (l, if channel_count == 1 { l } else { buffer[interleaved_index + 1] })
+            }
+            SampleData::Mapped(mapped) => mapped.frame(index),
+        }
+    }
+
+    /// Returns size of sample data in bytes, mapped data is paged in by the OS on demand.
+    pub fn byte_size(&self) -> usize {
+        match self {
+            SampleData::Buffer(buffer) => std::mem::size_of_val(buffer.as_ref()),
+            SampleData::Mapped(mapped) => mapped.mmap.len(),
+        }
+    }
+
+    /// Returns decoded, interleaved samples without padding, [None] if memory-mapped.
+    pub fn samples(&self, channel_count: u16) -> Option<&[f32]> {
+        match self {
+            SampleData::Buffer(buffer) => {
+                let padding = PADDING_FRAMES * channel_count as usize;
+                Some(&buffer[padding..buffer.len() - padding])
+            }
+            SampleData::Mapped(_) => None,
+        }
+    }
+
+    /// Returns number of frames (without padding).
+    pub fn frame_count(&self, channel_count: u16) -> usize {
+        match self {
+            SampleData::Buffer(buffer) => buffer.len() / channel_count as usize - 2 * PADDING_FRAMES,
+            SampleData::Mapped(mapped) => mapped.frame_count,
+        }
+    }
+}
+impl PartialEq for SampleData {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (SampleData::Buffer(buffer), SampleData::Buffer(other_buffer)) => buffer == other_buffer,
+            (SampleData::Mapped(mapped), SampleData::Mapped(other_mapped)) => {
+                mapped.mmap.as_ptr() == other_mapped.mmap.as_ptr()
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Memory-mapped WAV file, the OS pages sample data in on access and shares it between processes.
+///
+/// File must not be modified or truncated while mapped, so mapped samples can't be edited while playing.
+#[derive(Debug)]
+pub struct MappedWav {
+    /// Channels in audio file.
+    pub channel_count: u16,
+
+    /// Byte offset of first sample.
+    data_offset: usize,
+
+    /// Sample encoding.
+    encoding: WavEncoding,
+
+    /// Number of frames in data chunk.
+    pub frame_count: usize,
+
+    /// Mapped file.
+    mmap: memmap2::Mmap,
+
+    /// Sample rate in Hz.
+    pub sample_rate: f32,
+}
+impl MappedWav {
+    /// Maps WAV file, supports PCM (8/16/24/32 bit) and float (32/64 bit) samples.
+    pub fn open(file_path: &str) -> Result<Self, Error> {
+        let file = File::open(file_path)?;
+
+        // Safety: Sample libraries are read-only, behavior is undefined if the file is modified while mapped.
+        let mmap = unsafe { memmap2::Mmap::map(&file)? };
+        if mmap.get(0..4) != Some(b"RIFF") || mmap.get(8..12) != Some(b"WAVE") {
+            return Err(Error::new(ErrorKind::InvalidData, "Not a WAV file."));
+        }
+
+        // Find format and data chunks, chunks are padded to even size.
+        let mut format = None;
+        let mut data = None;
+        let mut position = 12;
+        while let Some(chunk_header) = mmap.get(position..position + 8) {
+            let size = u32::from_le_bytes(chunk_header[4..8].try_into().unwrap()) as usize;
+            let start = position + 8;
+            match &chunk_header[0..4] {
+                b"fmt " => format = mmap.get(start..start + size),
+                b"data" => data = Some((start, size.min(mmap.len() - start))),
+                _ => (),
+            }
+            position = start + size + size % 2;
+        }
+        let format = format.filter(|format| format.len() >= 16);
+        let format = format.ok_or_else(|| Error::new(ErrorKind::InvalidData, "Missing fmt chunk."))?;
+        let (data_offset, data_size) = data.ok_or_else(|| Error::new(ErrorKind::InvalidData, "Missing data chunk."))?;
+
+        // Parse format chunk, extensible format stores actual format tag in sub format.
+        let format_tag = match u16::from_le_bytes([format[0], format[1]]) {
+            0xFFFE if format.len() >= 26 => u16::from_le_bytes([format[24], format[25]]),
+            format_tag => format_tag,
+        };
+        let channel_count = u16::from_le_bytes([format[2], format[3]]);
+        let sample_rate = u32::from_le_bytes(format[4..8].try_into().unwrap()) as f32;
+        let block_align = u16::from_le_bytes([format[12], format[13]]) as usize;
+        if channel_count == 0 || !block_align.is_multiple_of(channel_count as usize) {
+            return Err(Error::new(ErrorKind::InvalidData, "Invalid block alignment."));
+        }
+        let encoding = match (format_tag, block_align / channel_count as usize) {
+            (1, 1) => WavEncoding::UnsignedInt8,
+            (1, 2) => WavEncoding::Int16,
+            (1, 3) => WavEncoding::Int24,
+            (1, 4) => WavEncoding::Int32,
+            (3, 4) => WavEncoding::Float32,
+            (3, 8) => WavEncoding::Float64,
+            _ => return Err(Error::new(ErrorKind::InvalidData, "Unsupported WAV encoding.")),
+        };
+
+        Ok(MappedWav { channel_count, data_offset, encoding, frame_count: data_size / block_align, mmap, sample_rate })
+    }
+
+    /// Returns stereo frame at index (zero outside of data chunk), mirrors left channel if mono.
+    #[inline(always)]
+    fn frame(&self, index: isize) -> (f32, f32) {
+        if index < 0 || index as usize >= self.frame_count {
+            return (0.0, 0.0);
+        }
+        let (index, channel_count) = (index as usize, self.channel_count);
+        let l = self.sample(index, 0);
+        (l, if channel_count == 1 { l } else { self.sample(index, 1) })
+    }
+
+    /// Converts sample at frame index and channel, samples are stored interleaved and little-endian.
+    #[inline(always)]
+    fn sample(&self, index: usize, channel: usize) -> f32 {
+        let sample_size = self.encoding.sample_size();
+        let offset = self.data_offset + (index * self.channel_count as usize + channel) * sample_size;
+        let bytes = &self.mmap[offset..offset + sample_size];
+        match self.encoding {
+            WavEncoding::UnsignedInt8 => (bytes[0] as f32 - 128.0) / 128.0,
+            WavEncoding::Int16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f32 / 32768.0,
+            WavEncoding::Int24 => (i32::from_le_bytes([0, bytes[0], bytes[1], bytes[2]]) >> 8) as f32 / 8388608.0,
+            WavEncoding::Int32 => i32::from_le_bytes(bytes.try_into().unwrap()) as f32 / 2147483648.0,
+            WavEncoding::Float32 => f32::from_le_bytes(bytes.try_into().unwrap()),
+            WavEncoding::Float64 => f64::from_le_bytes(bytes.try_into().unwrap()) as f32,
+        }
+    }
+}
+
+/// Sample encoding of a WAV file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum WavEncoding {
+    UnsignedInt8,
+    Int16,
+    Int24,
+    Int32,
+    Float32,
+    Float64,
+}
+impl WavEncoding {
+    /// Returns sample size in bytes.
+    fn sample_size(&self) -> usize {
+        match self {
+            WavEncoding::UnsignedInt8 => 1,
+            WavEncoding::Int16 => 2,
+            WavEncoding::Int24 => 3,
+            WavEncoding::Int32 | WavEncoding::Float32 => 4,
+            WavEncoding::Float64 => 8,
+        }
+    }
+}
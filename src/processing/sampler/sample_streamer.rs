@@ -0,0 +1,348 @@
+use super::{audio_file_sound::read_wav_samples, AudioFileSound};
+use ringbuf::{Consumer, Producer, RingBuffer};
+use std::{
+    fs::File,
+    io::BufReader,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+};
+
+/// Frames buffered per stream, covers reader latency (about 340 ms at 48 kHz and normal play rate).
+const STREAM_BUFFER_FRAMES: usize = 16384;
+
+/// Frames read per stream and pass, so that one stream cannot starve the others.
+const READ_CHUNK_FRAMES: usize = 2048;
+
+/// Streams sounds from disk into lock-free ring buffers of voices, using a background reader thread.
+///
+/// Reader thread sleeps while there is nothing to read, streams wake it up (without locking) when it has work.
+#[derive(Debug)]
+pub struct SampleStreamer {
+    /// Reader thread, woken up when a stream is created.
+    reader: thread::Thread,
+
+    /// Hands new streams over to reader thread.
+    stream_sender: mpsc::Sender<ReaderStream>,
+
+    /// Number of frames that were not available in time, shared with streams.
+    underrun_count: Arc<AtomicUsize>,
+}
+impl SampleStreamer {
+    /// Creates new streamer and starts reader thread (runs until streamer and all streams are dropped).
+    pub fn new() -> Self {
+        let (stream_sender, stream_receiver) = mpsc::channel();
+        let underrun_count = Arc::new(AtomicUsize::new(0));
+        let reader_underrun_count = underrun_count.clone();
+        let reader = thread::Builder::new()
+            .name(String::from("sample-streamer"))
+            .spawn(move || SampleStreamer::run(stream_receiver, reader_underrun_count))
+            .expect("Failed to start sample streamer thread.")
+            .thread()
+            .clone();
+        SampleStreamer { reader, stream_sender, underrun_count }
+    }
+
+    /// Creates new stream for a voice, allocates ring buffers (so don't call this from audio thread).
+    pub fn create_stream(&self) -> SampleStream {
+        let (frame_producer, frame_consumer) = RingBuffer::new(STREAM_BUFFER_FRAMES).split();
+        let (request_producer, request_consumer) = RingBuffer::new(16).split();
+        let closed = Arc::new(AtomicBool::new(false));
+        let generation = Arc::new(AtomicU32::new(0));
+        let reader_stream = ReaderStream {
+            closed: closed.clone(),
+            frames: frame_producer,
+            generation: generation.clone(),
+            job: None,
+            requests: request_consumer,
+        };
+        self.stream_sender.send(reader_stream).expect("Sample streamer thread stopped.");
+        self.reader.unpark();
+
+        SampleStream {
+            closed,
+            frames: frame_consumer,
+            generation,
+            local_generation: 0,
+            next_frame: 0,
+            reader: self.reader.clone(),
+            requests: request_producer,
+            underrun_count: self.underrun_count.clone(),
+            window: [(0.0, 0.0); 2],
+        }
+    }
+
+    /// Returns number of frames that were not available in time (buffer underruns) since creation.
+    #[allow(dead_code)]
+    pub fn underrun_count(&self) -> usize {
+        self.underrun_count.load(Ordering::Relaxed)
+    }
+
+    /// Reader thread, fills ring buffers of all streams round-robin and reports underruns.
+    fn run(stream_receiver: mpsc::Receiver<ReaderStream>, underrun_count: Arc<AtomicUsize>) {
+        let mut streams: Vec<ReaderStream> = Vec::new();
+        let mut reported_underrun_count = 0;
+        loop {
+            // Add new streams, wait for one if there are none. Stop once streamer and all streams have been dropped.
+            if streams.is_empty() {
+                match stream_receiver.recv() {
+                    Ok(stream) => streams.push(stream),
+                    Err(mpsc::RecvError) => return,
+                }
+            }
+            streams.extend(stream_receiver.try_iter());
+            streams.retain(|stream| !stream.closed.load(Ordering::Acquire));
+
+            // Fill ring buffers, sleep until woken up by a stream if there is nothing to read.
+            let mut did_read = false;
+            for stream in streams.iter_mut() {
+                did_read |= stream.fill();
+            }
+
+            // Report underruns here, as printing is not allowed on the audio thread.
+            let current_underrun_count = underrun_count.load(Ordering::Relaxed);
+            if current_underrun_count != reported_underrun_count {
+                eprintln!("[SampleStreamer] Buffer underruns: {} frames", current_underrun_count);
+                reported_underrun_count = current_underrun_count;
+            }
+            if !did_read && !streams.is_empty() {
+                thread::park();
+            }
+        }
+    }
+}
+
+/// Voice side of a stream, frames are pulled from a ring buffer without blocking.
+pub struct SampleStream {
+    /// Set on drop, so that reader thread removes its side of the stream.
+    closed: Arc<AtomicBool>,
+
+    /// Frames read by reader thread.
+    frames: Consumer<StreamFrame>,
+
+    /// Current stream generation, shared with reader thread to cancel outdated reads.
+    generation: Arc<AtomicU32>,
+
+    /// Current stream generation, used to skip outdated frames.
+    local_generation: u32,
+
+    /// Sample index of next frame in ring buffer.
+    next_frame: usize,
+
+    /// Reader thread, woken up by requests, freed buffer space and underruns.
+    reader: thread::Thread,
+
+    /// Stream requests to reader thread.
+    requests: Producer<StreamRequest>,
+
+    /// Number of frames that were not available in time.
+    underrun_count: Arc<AtomicUsize>,
+
+    /// Last two frames pulled from ring buffer, used for interpolation.
+    window: [(f32, f32); 2],
+}
+impl SampleStream {
+    /// Requests frames of a sound from start frame on, previous frame is used for interpolation.
+    pub fn start(&mut self, sound: Arc<AudioFileSound>, start_frame: usize, previous_frame: (f32, f32)) {
+        self.stop();
+        self.frames.discard(self.frames.len()); // Frames pushed after this are skipped by generation.
+        self.next_frame = start_frame;
+        self.window = [(0.0, 0.0), previous_frame];
+        let request = StreamRequest { generation: self.local_generation, sound, start_frame };
+        if self.requests.push(request).is_err() {
+            // Reader thread is not keeping up, all frames of this note will underrun.
+            self.underrun_count.fetch_add(1, Ordering::Relaxed);
+        }
+        self.reader.unpark();
+    }
+
+    /// Stops stream, reader thread cancels outstanding reads.
+    pub fn stop(&mut self) {
+        self.local_generation = self.local_generation.wrapping_add(1);
+        self.generation.store(self.local_generation, Ordering::Release);
+    }
+
+    /// Returns stereo sample value at position (via linear interpolation), or [None] on buffer underrun.
+    ///
+    /// Positions must not decrease, as frames are consumed from the ring buffer.
+    #[inline(always)]
+    pub fn get_value(&mut self, sample_position: f32) -> Option<(f32, f32)> {
+        // Pull frames until window contains frames at index and index + 1.
+        let index = sample_position as usize;
+        while self.next_frame < index + 2 {
+            match self.frames.pop() {
+                Some(frame) if frame.generation != self.local_generation => continue, // Outdated frame.
+                Some(frame) => {
+                    self.window = [self.window[1], frame.value];
+                    self.next_frame += 1;
+
+                    // Wake reader thread once a chunk of buffer space is free.
+                    if self.frames.remaining() == READ_CHUNK_FRAMES {
+                        self.reader.unpark();
+                    }
+                }
+                None => {
+                    self.underrun_count.fetch_add(1, Ordering::Relaxed);
+                    self.reader.unpark();
+                    return None;
+                }
+            }
+        }
+
+        let alpha = sample_position - index as f32;
+        let inv_alpha = 1.0 - alpha;
+        let (frame_0, frame_1) = (self.window[0], self.window[1]);
+        Some((inv_alpha * frame_0.0 + alpha * frame_1.0, inv_alpha * frame_0.1 + alpha * frame_1.1))
+    }
+}
+impl Drop for SampleStream {
+    fn drop(&mut self) {
+        self.closed.store(true, Ordering::Release);
+        self.reader.unpark();
+    }
+}
+impl std::fmt::Debug for SampleStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SampleStream")
+            .field("buffered_frames", &self.frames.len())
+            .field("generation", &self.local_generation)
+            .field("next_frame", &self.next_frame)
+            .finish()
+    }
+}
+
+/// Stereo frame, tagged with stream generation.
+#[derive(Clone, Copy, Debug)]
+struct StreamFrame {
+    generation: u32,
+    value: (f32, f32),
+}
+
+/// Request to stream a sound from start frame on.
+struct StreamRequest {
+    generation: u32,
+    sound: Arc<AudioFileSound>,
+    start_frame: usize,
+}
+
+/// Reader thread side of a stream.
+struct ReaderStream {
+    closed: Arc<AtomicBool>,
+    frames: Producer<StreamFrame>,
+    generation: Arc<AtomicU32>,
+    job: Option<StreamJob>,
+    requests: Consumer<StreamRequest>,
+}
+impl ReaderStream {
+    /// Reads next chunk of frames into ring buffer, returns true if frames were read.
+    fn fill(&mut self) -> bool {
+        // Only latest request is relevant.
+        while let Some(request) = self.requests.pop() {
+            self.job = StreamJob::open(&request)
+                .map_err(|error| eprintln!("[SampleStreamer] Failed to open stream: {}", error))
+                .ok();
+        }
+
+        // Cancel outdated job, e.g. if voice was stopped or restarted.
+        let job = match &mut self.job {
+            Some(job) if job.generation == self.generation.load(Ordering::Acquire) => job,
+            _ => {
+                self.job = None;
+                return false;
+            }
+        };
+
+        let frame_count = self.frames.remaining().min(READ_CHUNK_FRAMES);
+        if frame_count == 0 {
+            return false;
+        }
+        match job.read(frame_count, &mut self.frames) {
+            Ok(true) => (),
+            Ok(false) => self.job = None,
+            Err(error) => {
+                eprintln!("[SampleStreamer] Failed to read stream: {}", error);
+                self.job = None;
+            }
+        }
+        true
+    }
+}
+
+/// Open WAV file that is streamed into a ring buffer.
+struct StreamJob {
+    /// Sample index where sound ends, a zero frame is added after it (for interpolation).
+    end_frame: usize,
+    generation: u32,
+    next_frame: usize,
+    reader: hound::WavReader<BufReader<File>>,
+}
+impl StreamJob {
+    /// Opens streamed file of request and seeks to start frame.
+    fn open(request: &StreamRequest) -> Result<Self, hound::Error> {
+        let file_path = request.sound.stream_source().ok_or(hound::Error::Unsupported)?;
+        let mut reader = hound::WavReader::open(file_path)?;
+        let end_frame = request.sound.duration_samples.min(reader.duration() as usize);
+        let next_frame = request.start_frame.min(end_frame);
+        reader.seek(next_frame as u32)?;
+        Ok(StreamJob { end_frame, generation: request.generation, next_frame, reader })
+    }
+
+    /// Reads up to frame count frames into ring buffer, returns false if stream has ended.
+    fn read(&mut self, frame_count: usize, frames: &mut Producer<StreamFrame>) -> Result<bool, hound::Error> {
+        let channel_count = self.reader.spec().channels as usize;
+        let file_frame_count = frame_count.min(self.end_frame - self.next_frame);
+        let samples = read_wav_samples(&mut self.reader, file_frame_count * channel_count)?;
+        for frame in samples.chunks_exact(channel_count) {
+            // Mirror left channel if mono, ignore channels beyond stereo.
+            let value = (frame[0], frame[channel_count.min(2) - 1]);
+            frames.push(StreamFrame { generation: self.generation, value }).ok();
+        }
+        self.next_frame += file_frame_count;
+
+        // Add zero frame after end, if there is room left.
+        if self.next_frame >= self.end_frame && file_frame_count < frame_count {
+            frames.push(StreamFrame { generation: self.generation, value: (0.0, 0.0) }).ok();
+            return Ok(false);
+        }
+        Ok(true)
+    }
+}
+
+/// Unit tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_underrun() {
+        let streamer = SampleStreamer::new();
+        let mut stream = streamer.create_stream();
+
+        // Nothing has been requested, so there are no frames.
+        assert_eq!(stream.get_value(10.5), None);
+        assert_eq!(streamer.underrun_count(), 1);
+    }
+
+    #[test]
+    fn refill_buffer() {
+        let streamer = SampleStreamer::new();
+        let mut stream = streamer.create_stream();
+        let test_file = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/test/test_sine.wav");
+        let test_file = test_file.to_str().unwrap();
+        let sound = AudioFileSound::from_wav(test_file, (48, 40, 60, 18, 120), (0.001, 0.0, 0.0, 0.1)).unwrap();
+        let streamed =
+            AudioFileSound::from_wav_streamed(test_file, 256, (48, 40, 60, 18, 120), (0.001, 0.0, 0.0, 0.1)).unwrap();
+
+        // Sound is about three times as long as ring buffer, reader thread refills it as frames are pulled.
+        stream.start(Arc::new(streamed), 256, sound.get_value(255.0));
+        thread::sleep(std::time::Duration::from_millis(100)); // Give reader thread time to fill buffer.
+        for position in (256..sound.duration_samples - 1).step_by(1024) {
+            let value = stream.get_value(position as f32).unwrap();
+            assert!((value.0 - sound.get_value(position as f32).0).abs() < 1e-6);
+            thread::sleep(std::time::Duration::from_millis(2));
+        }
+        assert_eq!(streamer.underrun_count(), 0);
+    }
+}
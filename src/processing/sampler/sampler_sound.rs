@@ -1,5 +1,38 @@
+use std::sync::Arc;
+
 /// Defines a sound that can be played by a sampler voice, e.g. an oscillator config or audio file data.
 pub trait SamplerSound: Send + Sync {
     /// Returns whether sound applies to given midi note.
     fn applies_to_note(&self, midi_note: u8, midi_velocity: u8) -> bool;
+
+    /// Converts sounds to sample rate (e.g. engine sample rate), sounds that can't be converted are kept.
+    fn resample(_sounds: &mut [Arc<Self>], _sample_rate: f32)
+    where
+        Self: Sized,
+    {
+    }
+
+    /// Returns behavior when note is played again while voices of sound still play it.
+    fn same_note_behavior(&self) -> SameNoteBehavior {
+        SameNoteBehavior::default()
+    }
+}
+
+/// Behavior of a sound when its note is played again on a channel while voices of the sound still play it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SameNoteBehavior {
+    /// Whether masked voices are cut with a short fade-out instead of released (e.g. drums, SFZ `off_mode=fast`).
+    pub cut: bool,
+
+    /// Maximum voices of the sound playing the note (SFZ `note_polyphony`), [None] lets notes overlap without limit.
+    pub max_voices: Option<u8>,
+
+    /// Whether voices are only masked by notes of equal or higher velocity (SFZ `note_selfmask`), otherwise oldest.
+    pub self_mask: bool,
+}
+impl Default for SameNoteBehavior {
+    /// Voices playing the note are released.
+    fn default() -> Self {
+        SameNoteBehavior { cut: false, max_voices: Some(1), self_mask: false }
+    }
 }
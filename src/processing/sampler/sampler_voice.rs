@@ -1,12 +1,21 @@
 use std::sync::Arc;
 
-use super::SamplerSound;
+use super::{GlideCurve, SamplerSound};
 
 /// Defines a voice that a sampler can use to play a sampler sound.
 pub trait SamplerVoice<Sound: SamplerSound>: Send {
+    /// Changes pitch of playing note to MIDI note without restarting envelope and sound (legato).
+    fn change_note(&mut self, midi_note: u8);
+
+    /// Stops note with a short fade-out in seconds regardless of release (e.g. stolen voice).
+    fn fade_out(&mut self, fade_time: f32);
+
     /// Returns current MIDI note if playing, [None] otherwise.
     fn get_active_note(&self) -> Option<u8>;
 
+    /// Returns current output level (gain and envelope, used for voice stealing), 0.0 if not playing.
+    fn get_level(&self) -> f32;
+
     // Returns voice priority (used for voice stealing, voices with lower priority are stolen first).
     fn get_priority(&self) -> u32;
 
@@ -16,18 +25,41 @@ pub trait SamplerVoice<Sound: SamplerSound>: Send {
     /// Returns whether voice is currently in use.
     fn is_playing(&self) -> bool;
 
+    /// Returns whether note is released (in release stage or fading out).
+    fn is_releasing(&self) -> bool;
+
     /// Renders audio samples from sound into buffer (additive).
     fn render(&mut self, buffer: &mut [f32]);
 
     /// Resets internal parameters of the voice.
     fn reset(&mut self, sample_rate: f32, max_buffer_size: usize);
 
+    /// Sets channel pressure (0.0 to 1.0), applies to current and following notes.
+    fn set_channel_pressure(&mut self, pressure: f32);
+
     /// Sets key down state.
     fn set_key_down(&mut self, key_down: bool);
 
+    /// Sets pressure of playing note (0.0 to 1.0), cleared when next note starts.
+    fn set_note_pressure(&mut self, pressure: f32);
+
+    /// Sets release time scale of current note (e.g. half-pedaling), reset to 1.0 when a note starts.
+    fn set_release_scale(&mut self, release_scale: f32);
+
+    /// Sets pitch bend (-1.0 to 1.0), bend range in semitones overrides range of sound if set.
+    fn set_pitch_bend(&mut self, bend: f32, bend_range: Option<f32>);
+
+    /// Sets timbre (CC 74, e.g. MPE slide, 0.0 to 1.0), applies to current and following notes.
+    fn set_timbre(&mut self, timbre: f32);
+
     /// Plays a note on this voice.
     fn start_note(&mut self, midi_note: u8, velocity: f32, sound: Arc<Sound>, initial_priority: u32);
 
+    /// Starts glide of playing note from pitch offset in semitones (portamento), adds to ongoing glide.
+    ///
+    /// Glide takes time in seconds, or time per octave with constant rate curve.
+    fn start_glide(&mut self, semitones: f32, time: f32, curve: GlideCurve);
+
     /// Stops note.
     fn stop_note(&mut self, velocity: f32, allow_tail: bool);
 }
@@ -0,0 +1,16 @@
+/// Voice stealing policy, decides which voice is stolen when polyphony is exhausted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StealPolicy {
+    /// Oldest voice is stolen.
+    #[default]
+    Oldest,
+
+    /// Quietest voice (gain and envelope) is stolen.
+    Quietest,
+
+    /// Oldest released voice is stolen, oldest voice if no voice is released.
+    ReleasingFirst,
+
+    /// Oldest voice playing the new note is stolen, oldest voice if no voice plays it.
+    SameNoteFirst,
+}
@@ -3,12 +3,14 @@ use std::collections::HashMap;
 
 /// Dummy audio processor for testing purposes.
 pub struct DummyProcessor {
+    pub frame_offsets: Vec<usize>,
     pub midi_messages: Vec<MidiMessage>,
     parameter: HashMap<ParameterId, ParameterValue>,
 }
 impl DummyProcessor {
     pub fn new() -> DummyProcessor {
-        let mut processor = DummyProcessor { midi_messages: Vec::new(), parameter: HashMap::new() };
+        let mut processor =
+            DummyProcessor { frame_offsets: Vec::new(), midi_messages: Vec::new(), parameter: HashMap::new() };
         processor.set_parameter(0, ParameterValue::Float(0.0));
         processor.set_parameter(1, ParameterValue::Float(0.0));
         processor
@@ -37,6 +39,10 @@ impl AudioProcessor for DummyProcessor {
 }
 impl MidiReceiver for DummyProcessor {
     fn handle_midi_message(&mut self, message: MidiMessage) {
+        self.handle_midi_message_at(message, 0);
+    }
+    fn handle_midi_message_at(&mut self, message: MidiMessage, frame_offset: usize) {
+        self.frame_offsets.push(frame_offset);
         self.midi_messages.push(message);
     }
 }
@@ -1,21 +1,55 @@
-use crate::processing::{SamplerSound, SamplerVoice};
+use crate::processing::{GlideCurve, SameNoteBehavior, SamplerSound, SamplerVoice};
 
 /// Dummy sampler voice for testing purposes.
 pub struct DummyVoice {
     active_note: Option<u8>,
+    pub fading: bool,
+    pub glide: Option<(f32, f32, GlideCurve)>,
     key_down: bool,
+    pub pitch_bend: (f32, Option<f32>),
+    priority: u32,
+    pub pressure: (f32, f32),
+    pub release_scale: f32,
+    pub timbre: f32,
+    pub velocity: f32,
 }
 impl DummyVoice {
     pub fn new() -> Self {
-        DummyVoice { active_note: None, key_down: false }
+        DummyVoice {
+            active_note: None,
+            fading: false,
+            glide: None,
+            key_down: false,
+            pitch_bend: (0.0, None),
+            priority: 0,
+            pressure: (0.0, 0.0),
+            release_scale: 1.0,
+            timbre: 0.0,
+            velocity: 0.0,
+        }
     }
 }
 impl SamplerVoice<DummySound> for DummyVoice {
+    fn change_note(&mut self, midi_note: u8) {
+        if self.active_note.is_some() {
+            self.active_note = Some(midi_note);
+        }
+    }
+    fn fade_out(&mut self, _fade_time: f32) {
+        self.fading = true;
+    }
     fn get_active_note(&self) -> Option<u8> {
         self.active_note
     }
+    fn get_level(&self) -> f32 {
+        if self.is_playing() {
+            self.velocity
+        } else {
+            0.0
+        }
+    }
     fn get_priority(&self) -> u32 {
-        0
+        self.priority
     }
     fn is_key_down(&self) -> bool {
         self.key_down
@@ -23,6 +57,9 @@ impl SamplerVoice<DummySound> for DummyVoice {
     fn is_playing(&self) -> bool {
         self.active_note.is_some()
     }
+    fn is_releasing(&self) -> bool {
+        self.fading
+    }
     fn render(&mut self, buffer: &mut [f32]) {
         if let Some(note) = self.active_note {
             // Stereo.
@@ -35,11 +72,35 @@ impl SamplerVoice<DummySound> for DummyVoice {
     fn reset(&mut self, _sample_rate: f32, _max_buffer_size: usize) {
         return;
     }
+    fn set_channel_pressure(&mut self, pressure: f32) {
+        self.pressure.0 = pressure;
+    }
     fn set_key_down(&mut self, key_down: bool) {
         self.key_down = key_down
     }
-    fn start_note(&mut self, midi_note: u8, _velocity: f32, _sound: std::sync::Arc<DummySound>, _priority: u32) {
+    fn set_note_pressure(&mut self, pressure: f32) {
+        self.pressure.1 = pressure;
+    }
+    fn set_pitch_bend(&mut self, bend: f32, bend_range: Option<f32>) {
+        self.pitch_bend = (bend, bend_range);
+    }
+    fn set_release_scale(&mut self, release_scale: f32) {
+        self.release_scale = release_scale;
+    }
+    fn set_timbre(&mut self, timbre: f32) {
+        self.timbre = timbre;
+    }
+    fn start_note(&mut self, midi_note: u8, velocity: f32, _sound: std::sync::Arc<DummySound>, priority: u32) {
         self.active_note = Some(midi_note);
+        self.pressure.1 = 0.0;
+        self.fading = false;
+        self.glide = None;
+        self.priority = priority;
+        self.release_scale = 1.0;
+        self.velocity = velocity;
+    }
+    fn start_glide(&mut self, semitones: f32, time: f32, curve: GlideCurve) {
+        self.glide = Some((semitones, time, curve));
     }
     fn stop_note(&mut self, _velocity: f32, _allow_tail: bool) {
         self.active_note = None;
@@ -47,14 +108,19 @@ impl SamplerVoice<DummySound> for DummyVoice {
 }
 
 /// Dummy sampler sound for testing purposes.
-pub struct DummySound {}
+pub struct DummySound {
+    pub same_note: SameNoteBehavior,
+}
 impl DummySound {
     pub fn new() -> Self {
-        DummySound {}
+        DummySound { same_note: SameNoteBehavior::default() }
     }
 }
 impl SamplerSound for DummySound {
     fn applies_to_note(&self, _midi_note: u8, _midi_velocity: u8) -> bool {
         true
     }
+    fn same_note_behavior(&self) -> SameNoteBehavior {
+        self.same_note
+    }
 }
@@ -0,0 +1,49 @@
+import struct
+import wave
+
+
+def extended(value):
+    """Encodes an integer sample rate as 80 bit IEEE 754 extended precision float."""
+    exponent = value.bit_length() - 1
+    mantissa = value << (63 - exponent)
+    return struct.pack(">HQ", 16383 + exponent, mantissa)
+
+
+def chunk(chunk_id, data):
+    padding = b"\0" if len(data) % 2 else b""
+    return chunk_id + struct.pack(">I", len(data)) + data + padding
+
+
+def pstring(text):
+    data = bytes([len(text)]) + text
+    return data + (b"\0" if len(data) % 2 else b"")
+
+
+def aiff(form_type, comm, sound_data, extra_chunks=b""):
+    ssnd = chunk(b"SSND", struct.pack(">II", 0, 0) + sound_data)
+    body = form_type + chunk(b"COMM", comm) + extra_chunks + ssnd
+    return b"FORM" + struct.pack(">I", len(body)) + body
+
+
+# Read 1s sine test sample (480hz left, 240hz right).
+with wave.open("test_sine.wav") as wav:
+    channels, sample_rate, frame_count = wav.getnchannels(), wav.getframerate(), wav.getnframes()
+    samples = struct.unpack("<%dh" % (frame_count * channels), wav.readframes(frame_count))
+
+# AIFF: 16 bit big-endian PCM with markers and instrument chunk (base note 29, forward loop 1200 to 2400).
+comm = struct.pack(">hIh", channels, frame_count, 16) + extended(sample_rate)
+markers = struct.pack(">H", 2)
+markers += struct.pack(">hI", 1, 1200) + pstring(b"loop start")
+markers += struct.pack(">hI", 2, 2400) + pstring(b"loop end")
+instrument = struct.pack(">bbbbbbh", 29, 0, 0, 127, 1, 127, 0)
+instrument += struct.pack(">hhh", 1, 1, 2) + struct.pack(">hhh", 0, 0, 0)  # Sustain loop, release loop.
+sound_data = struct.pack(">%dh" % len(samples), *samples)
+with open("test_sine.aiff", "wb") as file:
+    file.write(aiff(b"AIFF", comm, sound_data, chunk(b"MARK", markers) + chunk(b"INST", instrument)))
+
+# AIFC: 32 bit big-endian float, left channel only.
+left = [s / 32768.0 for s in samples[0::channels]]
+comm = struct.pack(">hIh", 1, frame_count, 32) + extended(sample_rate) + b"fl32" + pstring(b"32-bit floating point")
+sound_data = struct.pack(">%df" % len(left), *left)
+with open("test_sine_float.aifc", "wb") as file:
+    file.write(aiff(b"AIFC", comm, sound_data, chunk(b"FVER", struct.pack(">I", 0xA2805140))))
@@ -0,0 +1,30 @@
+import struct
+
+# Create silent MPEG-1 Layer III test sample (48khz mono, 128kbps) with LAME tag holding encoder delay and padding.
+# Decoders remove 529 samples decoder delay plus the stated delay and padding: 10 * 1152 - (529 + 576) - (944 - 529).
+sample_rate, frame_count, frame_size, side_info_size = 48000, 10, 384, 17
+encoder_delay, encoder_padding = 576, 944
+header = bytes([0xFF, 0xFB, 0x94, 0xC4])  # No CRC, 128kbps, 48khz, mono.
+
+
+def crc16(data):
+    crc = 0
+    for byte in data:
+        crc ^= byte
+        for _ in range(8):
+            crc = (crc >> 1) ^ 0xA001 if crc & 1 else crc >> 1
+    return crc
+
+
+# Info frame (Xing tag for CBR files) with LAME extension.
+info = header + bytes(side_info_size) + b"Info" + struct.pack(">III", 0x3, frame_count, frame_count * frame_size)
+info += b"LAME3.100" + bytes([0, 0]) + bytes(8) + bytes([0, 128])
+info += (encoder_delay << 12 | encoder_padding).to_bytes(3, "big")
+info += bytes([0, 0]) + struct.pack(">HIH", 0, frame_count * frame_size, 0)
+info += struct.pack(">H", crc16(info))
+info += bytes(frame_size - len(info))
+
+# Silent frames: zeroed side info means no Huffman data, main data is ignored.
+silent_frame = header + bytes(frame_size - len(header))
+with open("test_silence.mp3", "wb") as file:
+    file.write(info + silent_frame * frame_count)
@@ -0,0 +1,29 @@
+import zipfile
+
+# Create multisample test package (Bitwig format), reusing the sine test sample.
+manifest = """<?xml version="1.0" encoding="UTF-8"?>
+<multisample name="Test">
+    <generator>sampler</generator>
+    <layer name="Soft">
+        <sample file="test_sine.wav" gain="0.00" sample-start="0.000" sample-stop="48000.000" tune="0.0">
+            <key root="29" low="24" high="32"/>
+            <velocity low="1" high="47"/>
+        </sample>
+    </layer>
+    <layer name="Loud">
+        <sample file="test_sine.wav" gain="-6.02" sample-start="0.000" sample-stop="48000.000" tune="-0.5">
+            <key root="24" low="34" high="48"/>
+            <velocity low="48" high="72"/>
+            <loop mode="off" start="0.000" stop="48000.000"/>
+        </sample>
+        <sample file="test_sine.wav" gain="0.00" sample-start="100.000" sample-stop="24000.000" tune="0.0">
+            <key root="24" low="49" high="56"/>
+            <velocity low="48" high="72"/>
+            <loop mode="sustain" start="1200.000" stop="2400.000"/>
+        </sample>
+    </layer>
+</multisample>
+"""
+with zipfile.ZipFile("test.multisample", "w", zipfile.ZIP_DEFLATED) as archive:
+    archive.writestr("multisample.xml", manifest)
+    archive.write("test_sine.wav", "test_sine.wav")
@@ -8,3 +8,9 @@ y_left = np.sin(2 * np.pi * 480 * t)
 y_right = np.sin(2 * np.pi * 240 * t)
 y = np.column_stack((y_left, y_right))
 sf.write("test_sine.wav", y, sample_rate, "PCM_16")
+sf.write("test_sine.flac", y, sample_rate, "PCM_16")
+
+# Create 0.1s mono sine test sample (480hz) with 24 bit resolution.
+t = np.arange(sample_rate // 10) / sample_rate
+y_mono = np.sin(2 * np.pi * 480 * t)
+sf.write("test_sine_mono_24.flac", y_mono, sample_rate, "PCM_24")